@@ -1,231 +1,23 @@
-#![allow(clippy::unusual_byte_groupings)]
-
 use indicatif::ProgressBar;
 use indicatif::ProgressStyle;
-use permutation_string::*;
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeSet;
-use std::collections::HashMap;
 use std::collections::HashSet;
 use std::fs::File;
 use std::io::prelude::*;
-use time_2d_inversible_automata::*;
-
-const PAT0: u64 = repeat_bit(0b000, 3);
-const PAT1: u64 = repeat_bit(0b001, 3);
-const PAT2: u64 = repeat_bit(0b010, 3);
-const PAT3: u64 = repeat_bit(0b011, 3);
-const PAT4: u64 = repeat_bit(0b100, 3);
-const PAT5: u64 = repeat_bit(0b101, 3);
-const PAT6: u64 = repeat_bit(0b110, 3);
-const PAT7: u64 = repeat_bit(0b111, 3);
-
-const ALL_ONES: u64 = repeat_bit(0b1, 1);
-
-const fn replace(x: u64, pat1: u64, pat2: u64) -> u64 {
-    const BITS_FIRST: u64 = repeat_bit(0b001, 3);
-    let x1 = !(x ^ pat1);
-    let x2 = x1 & (x1 >> 1) & (x1 >> 2) & BITS_FIRST;
-    let x3 = x2 | (x2 << 1) | (x2 << 2);
-    x3 & pat2
-}
-
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
-pub struct Rule([u64; 8]);
-
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
-pub struct Field {
-    val: u64,
-    size: u32,
-}
-
-impl Rule {
-    pub const RULE0: Rule = Rule([PAT0, PAT1, PAT2, PAT3, PAT4, PAT5, PAT6, PAT7]);
-
-    pub fn new(x: [u64; 8]) -> Self {
-        Self(x)
-    }
-
-    pub fn num_to_rule(ni: u64) -> Rule {
-        let p = PermutationInt::new(ni, 8);
-        let p = PermutationIndex::try_from(p).unwrap();
-        let p = PermutationArray::try_from(p).unwrap();
-        let p: [usize; 8] = p.0.try_into().unwrap();
-        Rule(p.map(|x| Self::RULE0.0[x]))
-    }
-
-    pub fn num_to_rule_name(ni: u64) -> String {
-        let p = PermutationInt::new(ni, 8);
-        let p = PermutationIndex::try_from(p).unwrap();
-        let p = PermutationArray::try_from(p).unwrap();
-        let p: [usize; 8] = p.0.try_into().unwrap();
-        let mut res = String::new();
-        for i in p {
-            res.push(char::from(b'0' + i as u8));
-        }
-        res
-    }
-
-    pub fn replace_all(&self, mut x: &mut Field) {
-        let &Rule([r0, r1, r2, r3, r4, r5, r6, r7]) = self;
-        x.val = replace(x.val, PAT0, r0)
-            | replace(x.val, PAT1, r1)
-            | replace(x.val, PAT2, r2)
-            | replace(x.val, PAT3, r3)
-            | replace(x.val, PAT4, r4)
-            | replace(x.val, PAT5, r5)
-            | replace(x.val, PAT6, r6)
-            | replace(x.val, PAT7, r7);
-    }
-
-    pub fn step0(&self, x: &mut Field) {
-        self.replace_all(x);
-    }
-
-    pub fn step1(&self, x: &mut Field) {
-        *x = x.rotate_right(1);
-        self.replace_all(x);
-        *x = x.rotate_left(1);
-    }
-
-    pub fn step2(&self, x: &mut Field) {
-        *x = x.rotate_right(1);
-        *x = x.rotate_right(1);
-        self.replace_all(x);
-        *x = x.rotate_left(1);
-        *x = x.rotate_left(1);
-    }
-
-    pub fn steps(&self, x: &mut Field) {
-        self.replace_all(x);
-        *x = x.rotate_right(1);
-        self.replace_all(x);
-        *x = x.rotate_right(1);
-        self.replace_all(x);
-        *x = x.rotate_left(1);
-        *x = x.rotate_left(1);
-    }
-
-    pub fn steps_count(&self, x: &mut Field, count: u64) {
-        for _ in 0..count {
-            self.steps(x);
-        }
-    }
-
-    pub fn show_field(
-        &self,
-        mut x: Field,
-        steps: u64,
-        dupl: u64,
-        ni: u64,
-        img_name: &str,
-    ) -> String {
-        let x_start = x.val;
-        let mut result = vec![x.to_bools(dupl)];
-        for _ in 0..steps / 3 {
-            self.step0(&mut x);
-            result.push(x.to_bools(dupl));
-            self.step1(&mut x);
-            result.push(x.to_bools(dupl));
-            self.step2(&mut x);
-            result.push(x.to_bools(dupl));
-        }
-        std::fs::create_dir_all(format!("data/img_{}", img_name)).unwrap();
-        let name = format!(
-            "img_{}/{}_{}_{}_{}_{}.png",
-            img_name, x_start, x.size, steps, dupl, ni
-        );
-        draw_image(&format!("data/{}", name), result);
-        name
-    }
-}
-
-impl Field {
-    pub fn new(val: u64, size: u32) -> Self {
-        assert!(size % 3 == 0);
-        assert!(size <= 64);
-        assert!(val == (val & !(ALL_ONES << size)));
-        Self { val, size }
-    }
-
-    pub fn rotate_left(mut self, mut count: u32) -> Self {
-        count %= self.size;
-        self.val = ((self.val << count)
-            | ((self.val >> (self.size - count)) & (PAT7 >> (self.size - count))))
-            & !(ALL_ONES << self.size);
-        self
-    }
-
-    pub fn rotate_right(mut self, mut count: u32) -> Self {
-        count %= self.size;
-        self.val = ((self.val >> count)
-            | ((self.val & (PAT7 >> (self.size - count))) << (self.size - count)))
-            & !(ALL_ONES << self.size);
-        self
-    }
-
-    pub fn rotate(self, count: i32) -> Self {
-        if count < 0 {
-            self.rotate_left((-count) as u32)
-        } else {
-            self.rotate_right(count as u32)
-        }
-    }
-
-    pub fn to_bools(&self, dupl: u64) -> Vec<bool> {
-        let size = self.size as usize;
-        let mut result = vec![false; size];
-        for i in 0..size {
-            result[size - i - 1] = ((self.val >> i) & 1) != 0;
-        }
-        let mut result2 = Vec::new();
-        for _ in 0..dupl {
-            result2.extend(result.iter());
-        }
-        result2
-    }
-
-    pub fn occupied_size3(&self) -> u32 {
-        occupied_size3(self.val)
-    }
-
-    pub fn minimize(mut self) -> Self {
-        let mut my_min = self.val;
-        for i in 0..=self.size / 3 {
-            my_min = my_min.min(self.rotate_left(i * 3).val);
-        }
-        self.val = my_min;
-        self
-    }
-
-    pub fn centralize(self) -> Self {
-        let mut offset = (self.size - self.occupied_size3()) / 2;
-        offset -= offset % 3;
-        self.rotate_left(offset)
-    }
-
-    pub fn index(&self, pos: u32) -> bool {
-        ((self.val >> pos) & 1) != 0
-    }
-}
-
-pub fn occupied_size3(mut x: u64) -> u32 {
-    let mut count = 0;
-    while x != 0 {
-        x >>= 3;
-        count += 3;
-    }
-    count
-}
-
-pub fn occupied_size(mut x: u64) -> u32 {
-    let mut count = 0;
-    while x != 0 {
-        x >>= 1;
-        count += 1;
-    }
-    count
-}
+use time_2d_inversible_automata::block::batch;
+use time_2d_inversible_automata::block::classes::{canonical_rules, rule_classes, Symmetry};
+use time_2d_inversible_automata::block::commute::check_commute;
+use time_2d_inversible_automata::block::complexity::{complexity, Settings};
+use time_2d_inversible_automata::block::damage::{damage_spreading, show_damage};
+use time_2d_inversible_automata::block::ether::{find_ether_gliders, find_ethers};
+use time_2d_inversible_automata::block::invariants::{
+    check_size, conserved_quantities_with, trivial_quantities,
+};
+use time_2d_inversible_automata::block::spaceships::find_spaceships;
+use time_2d_inversible_automata::block::time2d::TimeLattice;
+use time_2d_inversible_automata::window3::gliders::*;
+use time_2d_inversible_automata::window3::*;
+use time_2d_inversible_automata::Random;
 
 pub fn print_col(file: &mut File, ni: u64, img: &str, info: &str) {
     writeln!(file, "<div class=\"automata-col\">",).unwrap();
@@ -240,133 +32,6 @@ pub fn print_col(file: &mut File, ni: u64, img: &str, info: &str) {
     writeln!(file, "</div>").unwrap();
 }
 
-// находит период и смещение глайдера, осциллятора или статичной картинки
-fn period(mut x: Field, rule: &Rule) -> Option<(u64, i32)> {
-    let y = x;
-    for period in 1..60 {
-        rule.steps(&mut x);
-        for offset in 0..4 {
-            if x.rotate_left(offset * 3) == y {
-                return Some((period, -(offset as i32)));
-            }
-            if x.rotate_right(offset * 3) == y {
-                return Some((period, (offset as i32)));
-            }
-        }
-    }
-    None
-}
-
-fn is_same_after(mut x: Field, rule: &Rule, steps: u64, offset: i32) -> bool {
-    let y = x;
-    rule.steps_count(&mut x, steps);
-    x.rotate(offset * 3) == y
-}
-
-// находит минимальное число описывающее глайдер
-fn minimize2(mut x: Field, rule: &Rule, period: u64) -> Field {
-    let mut my_min = x.val;
-    for _ in 0..period {
-        rule.steps(&mut x);
-        x = x.minimize();
-        my_min = my_min.min(x.val);
-    }
-    Field::new(my_min, x.size)
-}
-
-fn size_round3(x: u32) -> u32 {
-    x / 3 + (((x % 3) != 0) as u32)
-}
-
-fn size_round2(x: u32) -> u32 {
-    x / 2 + (((x % 2) != 0) as u32)
-}
-
-fn check_reach_everything(mut x: Field, rule: &Rule, period: u64) -> bool {
-    let pos_start = (|| {
-        for i in 0..x.size {
-            if x.index(i) {
-                return i;
-            }
-        }
-        unreachable!()
-    })();
-
-    let mut reached = Field::new(1 << pos_start, x.size);
-
-    let mut prev_x = x;
-
-    for step in 0..=period * 3 {
-        let mut reached2 = Field::new(0, x.size);
-
-        let pos_start = (|| {
-            for i in 0..x.size {
-                if !x.index(i) {
-                    return i;
-                }
-            }
-            unreachable!()
-        })();
-
-        let mut state = 0;
-        let mut current = 0;
-        let mut used = false;
-        for i in (0..x.size).map(|i| (pos_start + i) % x.size) {
-            if x.index(i) {
-                if state == 0 {
-                    state = 1;
-                    used = reached.index(i);
-                    current = 1 << i;
-                } else {
-                    used |= reached.index(i);
-                    current |= 1 << i;
-                }
-            } else if state == 1 {
-                state = 0;
-            }
-            if used {
-                reached2.val |= current;
-            }
-        }
-
-        reached = reached2;
-
-        prev_x = x;
-
-        if step % 3 == 0 {
-            rule.step0(&mut x);
-        } else if step % 3 == 1 {
-            rule.step1(&mut x);
-        } else {
-            rule.step2(&mut x);
-        }
-    }
-
-    reached.val == prev_x.val
-}
-
-// В цикличном массиве находит в каком месте начинается данный паттерн и его длину. Для этого находит нули максимального размера чтобы считать их пустым полем
-fn find_pattern_start(x: Field) -> i32 {
-    let mut my_min = x.val;
-    let mut min_offset: i32 = 0;
-
-    for i in 1..=size_round2(x.size / 3) {
-        let new = x.rotate_left(i * 3);
-        if new.val < my_min {
-            my_min = new.val;
-            min_offset = -(i as i32);
-        }
-
-        let new = x.rotate_right(i * 3);
-        if new.val < my_min {
-            my_min = new.val;
-            min_offset = i as i32;
-        }
-    }
-
-    min_offset
-}
-
 struct Bits(u64);
 
 impl std::fmt::Debug for Bits {
@@ -375,103 +40,6 @@ impl std::fmt::Debug for Bits {
     }
 }
 
-fn add_used(mut x: Field, period: u64, rule: &Rule, used: &mut HashMap<u64, (u64, u64, i32)>) {
-    for _ in 0..=period {
-        let start_offset = find_pattern_start(x);
-        let prev = x;
-        rule.steps(&mut x);
-        used.insert(
-            prev.minimize().val,
-            (
-                period,
-                x.minimize().val,
-                find_pattern_start(x) - start_offset,
-            ),
-        );
-    }
-}
-
-// предполагается что x уже минимизировано
-fn check_used(
-    mut x: Field,
-    period: u64,
-    rule: &Rule,
-    used: &HashMap<u64, (u64, u64, i32)>,
-) -> bool {
-    let size = occupied_size(x.val);
-    let y = x;
-    'outer: for i in 1..=size {
-        x = y;
-        let pat = !(1 << 63) >> (63 - i);
-        let possible_glider = x.val & pat;
-        if let Some((period_local, mut next, mut offset_local)) =
-            used.get(&possible_glider).cloned()
-        {
-            if period % period_local == 0 {
-                let mut offset = offset_local;
-                for _ in 0..period {
-                    rule.steps(&mut x);
-                    let pat = !(1 << 63) >> (63 - occupied_size(next));
-                    let now_sub = x.rotate(offset * 3).val & pat;
-                    if now_sub != next {
-                        continue 'outer;
-                    }
-
-                    let (_, b, c) = *used.get(&next).unwrap();
-                    next = b;
-                    offset_local = c;
-
-                    offset += offset_local;
-                }
-                return true;
-            }
-        }
-    }
-    false
-}
-
-// Определяет является ли данный паттерн глайдером и находит его минимальную форму
-fn is_this_glider(x: u64, rule: &Rule) -> Option<(Field, u64, i32)> {
-    let size = size_round3(occupied_size3(x));
-    if (size + 9) * 3 > 63 {
-        return None;
-    }
-    let (period, offset) = period(Field::new(x, (size + 3) * 3), rule)?;
-    for i in [4, 5, 6, 9] {
-        if !is_same_after(Field::new(x, (size + i) * 3), rule, period, offset) {
-            return None;
-        }
-    }
-
-    Some((
-        minimize2(Field::new(x, (size + 5) * 3), rule, period),
-        period,
-        offset,
-    ))
-}
-
-// Находит всех уникальных глайдеров и осцилляторов для данного правила, перебирая все числа до max_count и переводя их в битовое представление
-fn get_gliders(rule: &Rule, max_count: u64, use_progress: bool) -> BTreeSet<(u64, u64, i32)> {
-    let mut gliders = BTreeSet::new();
-    let mut used: HashMap<u64, (u64, u64, i32)> = HashMap::new(); // val, (period, next, offset)
-    let progress = ProgressBar::new(max_count).with_style(
-        ProgressStyle::default_bar()
-            .template("[elapsed: {elapsed:>6} | remaining: {eta:>6}] {wide_bar}"),
-    );
-    for x in 1..max_count {
-        if use_progress {
-            progress.inc(1);
-        }
-        if let Some((min, period, offset)) = is_this_glider(x, rule) {
-            if !check_used(min, period, rule, &used) {
-                add_used(min, period, rule, &mut used);
-                gliders.insert((min.val, period, offset));
-            }
-        }
-    }
-    gliders
-}
-
 fn show_gliders_for_all_rules() {
     let mut table = File::create("data/table.html").unwrap();
     let mut csv = File::create("data/table.csv").unwrap();
@@ -521,9 +89,21 @@ td:nth-child(7) {{ background-color: #cbf9cb; }}
         cell!("Gldr < #");
         cell!("Gldr > #");
         cell!("Gldr uniq");
+        cell!("Class");
+        cell!("Conserved");
+        cell!("Dmg <");
+        cell!("Dmg >");
+        cell!("Dmg dens");
+        cell!("Behaviour");
     }
 
-    writeln!(csv, "rule,osc_n,osc_uniq,gldr,gldr_l,gldr_r,gldr_uniq").unwrap();
+    writeln!(
+        csv,
+        "rule,osc_n,osc_uniq,gldr,gldr_l,gldr_r,gldr_uniq,class,conserved,dmg_l,dmg_r,dmg_d,ent_s,ent_t,lz,acorr,behaviour"
+    )
+    .unwrap();
+
+    let canonical = canonical_rules(&rule_classes::<3>(&[Symmetry::MirrorTime]));
 
     macro_rules! cell {
         ($a:expr) => {
@@ -535,6 +115,7 @@ td:nth-child(7) {{ background-color: #cbf9cb; }}
         };
     }
 
+    let trivial = trivial_quantities(2, check_size(&Rule::RULE0, 2));
     let progress = ProgressBar::new(5100).with_style(
         ProgressStyle::default_bar()
             .template("[elapsed: {elapsed:>6} | remaining: {eta:>6}] {wide_bar}"),
@@ -553,15 +134,29 @@ td:nth-child(7) {{ background-color: #cbf9cb; }}
         writeln!(gliders_file, "{}", BEFORE).unwrap();
         writeln!(oscillators_file, "{}", BEFORE).unwrap();
         let size = 100;
-        for ni in (0..size).map(|x| x + hundreds * 100) {
+        let rules = (0..size)
+            .map(|x| Rule::num_to_rule(x + hundreds * 100))
+            .collect::<Vec<_>>();
+        let all_gliders = batch::get_gliders(&rules, 10_000, Some(200));
+        for ((ni, rule), gliders) in (0..size)
+            .map(|x| x + hundreds * 100)
+            .zip(rules)
+            .zip(all_gliders)
+        {
             progress.inc(1);
 
             writeln!(gliders_file, "<div id='{}'></div>", ni).unwrap();
             writeln!(oscillators_file, "<div id='{}'></div>", ni).unwrap();
 
-            let rule = Rule::num_to_rule(ni);
-
-            let gliders = get_gliders(&rule, 10_000, false);
+            let conserved = conserved_quantities_with(&rule, &trivial)
+                .iter()
+                .map(|invariant| invariant.to_string())
+                .collect::<Vec<_>>();
+            let mut random = Random::new(25025069121927896);
+            let damage = damage_spreading(&rule, 100, 20, &mut random);
+            let complexity = complexity(&rule, &Settings::default(), &mut random);
+            let damage_img =
+                SHOW_DAMAGE.then(|| show_damage(&rule, 300, &mut random, ni, "damage"));
 
             writeln!(gliders_file, "{}", CONTAINER_START).unwrap();
             writeln!(oscillators_file, "{}", CONTAINER_START).unwrap();
@@ -604,6 +199,7 @@ td:nth-child(7) {{ background-color: #cbf9cb; }}
                     gliders_p_o_uniq.insert((period, offset));
                 }
             }
+            let behaviour = complexity.behaviour(gliders_left_count + gliders_right_count);
 
             row! {
                 cell!(ni);
@@ -621,18 +217,38 @@ td:nth-child(7) {{ background-color: #cbf9cb; }}
                 cell!(gliders_left_count);
                 cell!(gliders_right_count);
                 cell!(gliders_p_o_uniq.len());
+                writeln!(table, "<td>{}</td>", canonical[ni as usize]).unwrap();
+                writeln!(table, "<td>{}</td>", conserved.join("<br>")).unwrap();
+                if let Some(damage_img) = &damage_img {
+                    writeln!(table, "<td><a href='{}'>{:.2}</a></td>", damage_img, damage.left_speed()).unwrap();
+                } else {
+                    writeln!(table, "<td>{:.2}</td>", damage.left_speed()).unwrap();
+                }
+                writeln!(table, "<td>{:.2}</td>", damage.right_speed()).unwrap();
+                writeln!(table, "<td>{:.2}</td>", damage.mean_density()).unwrap();
+                writeln!(table, "<td>{}</td>", behaviour).unwrap();
             }
 
             writeln!(
                 csv,
-                "{},{},{},{},{},{},{}",
+                "{},{},{},{},{},{},{},{},{},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3},{}",
                 ni,
                 oscillators_count,
                 oscillators_p_o_uniq.len(),
                 gliders_left_count + gliders_right_count,
                 gliders_left_count,
                 gliders_right_count,
-                gliders_p_o_uniq.len()
+                gliders_p_o_uniq.len(),
+                canonical[ni as usize],
+                conserved.join("; "),
+                damage.left_speed(),
+                damage.right_speed(),
+                damage.mean_density(),
+                complexity.spatial_entropy,
+                complexity.temporal_entropy,
+                complexity.compression,
+                complexity.autocorrelation,
+                behaviour
             )
             .unwrap();
         }
@@ -645,7 +261,7 @@ td:nth-child(7) {{ background-color: #cbf9cb; }}
 fn find_more_gliders(ni: u64) {
     let mut file = File::create(&format!("data/gliders{}.html", ni)).unwrap();
     let rule = Rule::num_to_rule(ni);
-    let gliders = get_gliders(&rule, 100_000_000, true);
+    let gliders = get_gliders(&rule, 100_000_000, Some(200), true);
 
     writeln!(file, "{}", BEFORE).unwrap();
     writeln!(file, "{}", CONTAINER_START).unwrap();
@@ -668,6 +284,71 @@ fn find_more_gliders(ni: u64) {
     writeln!(file, "{}", AFTER).unwrap();
 }
 
+fn find_wide_gliders(ni: u64, max_period: u64, width: u32) {
+    let mut file = File::create(format!("data/wide_gliders{}.html", ni)).unwrap();
+    let rule = Rule::num_to_rule(ni);
+
+    writeln!(file, "{}", BEFORE).unwrap();
+    writeln!(file, "{}", CONTAINER_START).unwrap();
+    for period in 1..=max_period {
+        for offset in -(period as i32)..=period as i32 {
+            for ship in find_spaceships(&rule, period, offset, width) {
+                print_col(
+                    &mut file,
+                    ni,
+                    &rule.show_field(
+                        Field::new(ship.pattern, 63).centralize(),
+                        period * 3 * 2,
+                        1,
+                        ni,
+                        &format!("wide_gliders_{}", ni),
+                    ),
+                    &format!("n{} p{} o{}", ship.pattern, period, offset),
+                );
+            }
+        }
+    }
+    writeln!(file, "{}", CONTAINER_END).unwrap();
+    writeln!(file, "{}", AFTER).unwrap();
+}
+
+fn find_gliders_in_ether(ni: u64) {
+    let mut file = File::create(format!("data/ether_gliders{}.html", ni)).unwrap();
+    let rule = Rule::num_to_rule(ni);
+
+    writeln!(file, "{}", BEFORE).unwrap();
+    for ether in find_ethers(&rule, 3, 30) {
+        let gliders = find_ether_gliders(&rule, &ether, 1 << 12, 200);
+        writeln!(file, "{}", CONTAINER_START).unwrap();
+        for glider in gliders.iter().filter(|glider| glider.offset != 0) {
+            // кольцо из повторённого фона с дефектом посередине
+            let background = ether.background;
+            let size = 63 / background.size() * background.size();
+            let repeated = (0..size / background.size()).fold(0, |acc, i| {
+                acc | background.val() << (i * background.size())
+            });
+            let middle = size / 2 / background.size() * background.size();
+            let field = Field::new(repeated ^ (glider.defect << middle), size);
+            print_col(
+                &mut file,
+                ni,
+                &rule.show_field(field, 3 * 100, 1, ni, &format!("ether_gliders_{}", ni)),
+                &format!(
+                    "ether {:b} p{} o{}, n{} p{} o{}",
+                    background.val(),
+                    ether.period,
+                    ether.offset,
+                    glider.defect,
+                    glider.period,
+                    glider.offset
+                ),
+            );
+        }
+        writeln!(file, "{}", CONTAINER_END).unwrap();
+    }
+    writeln!(file, "{}", AFTER).unwrap();
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 struct TableElem {
     rule: u64,
@@ -683,20 +364,31 @@ struct TableElem {
     gliders_right: u64,
     #[serde(rename = "gldr_uniq")]
     gliders_uniq: u64,
+    #[serde(rename = "dmg_l")]
+    damage_left: f64,
+    #[serde(rename = "dmg_r")]
+    damage_right: f64,
+    #[serde(rename = "dmg_d")]
+    damage_density: f64,
+    behaviour: String,
 }
 
 impl std::fmt::Display for TableElem {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "{:5}: o({:3} {:3}) g({:3} | {:3} | {:3} | {:3})",
+            "{:5}: o({:3} {:3}) g({:3} | {:3} | {:3} | {:3}) d({:.2} | {:.2} | {:.2}) {}",
             self.rule,
             self.oscillators_count,
             self.oscillators_uniq,
             self.gliders_count,
             self.gliders_left,
             self.gliders_right,
-            self.gliders_uniq
+            self.gliders_uniq,
+            self.damage_left,
+            self.damage_right,
+            self.damage_density,
+            self.behaviour
         )
     }
 }
@@ -723,57 +415,14 @@ fn find_cool_rules() {
         // let b = x.gliders_right as f64;
         // (a.max(b) / a.min(b) * 10000.) as i32
 
-        -(x.oscillators_count as i64)
+        // сначала правила с частицами на фоне, потом хаотичные
+        (x.behaviour != "complex", -(x.oscillators_count as i64))
     });
     for i in elems.iter().take(20) {
         println!("{}", i);
     }
 }
 
-fn is_this_glider_gun(val: u64, rule: &Rule) -> Option<(u64, u64, u64, i32)> {
-    let size = occupied_size3(val);
-    let pat = (!0u64) >> (64 - size);
-    let mut x = Field::new(val, 60);
-    rule.steps(&mut x);
-    let mut count = 1;
-
-    while x.val != val && count < 15 {
-        if (x.val & pat) == val && (x.val & !pat) != 0 {
-            let x_without_gun = Field::new(x.val & !pat, x.size).minimize();
-
-            let mut x0 = Field::new(x.val, 60);
-            rule.steps_count(&mut x0, count);
-            if (x0.val & pat) == val && (x0.val & !pat) != 0 {
-                let x0_without_gun = Field::new(x0.val & !pat, x0.size).minimize();
-
-                let mut x1 = Field::new(val, 63);
-                rule.steps_count(&mut x1, count);
-                if (x1.val & pat) == val && (x1.val & !pat) != 0 {
-                    let x1_without_gun = Field::new(x1.val & !pat, x1.size).minimize();
-
-                    if x_without_gun.val == x1_without_gun.val {
-                        if let Some(((min, period, offset), (_, period0, offset0))) =
-                            is_this_glider(x_without_gun.val, rule)
-                                .zip(is_this_glider(x0_without_gun.val, rule))
-                        {
-                            if period == period0
-                                && offset == offset0
-                                && offset != 0
-                                && period <= count
-                            {
-                                return Some((count, min.val, period, offset));
-                            }
-                        }
-                    }
-                }
-            }
-        }
-        rule.steps(&mut x);
-        count += 1;
-    }
-    None
-}
-
 fn find_all_glider_guns_rules() {
     let elems = rules_data()
         .into_iter()
@@ -836,318 +485,70 @@ fn find_all_glider_guns_rules() {
 }
 
 fn find_commute_rules_2d_time() {
-    let mut random = Random::new(25025069121927896);
-    let starts = (0..10)
-        .map(|_| Field::new(random.get() & !(1 << 63), 63))
-        .collect::<Vec<_>>();
-
-    let commute_with_everyone = [
-        137, 2210, 2298, 2932, 2943, 4203, 4934, 13382, 14751, 16566, 16703, 18419, 19786, 21900,
-        23753, 25568, 26301, 26937, 28783, 32341, 35152, 35385, 36116, 37376, 40182,
-    ];
-
     let rules = rules_data()
         .into_iter()
         .filter(|x| x.gliders_count > 2)
-        .map(|x| x.rule)
-        .filter(|ni| !commute_with_everyone.contains(ni))
-        .map(|ni| (ni, Rule::num_to_rule(ni)))
+        .map(|x| (x.rule, Rule::num_to_rule(x.rule)))
         .collect::<Vec<_>>();
 
+    let mut pairs = Vec::new();
     for (i, (nia, a)) in rules.iter().enumerate() {
-        let mut first = true;
-        for (nib, b) in rules.iter().skip(i + 1) {
+        for (j, (nib, b)) in rules.iter().enumerate().skip(i + 1) {
             assert!(nib > nia);
-            if starts.iter().all(|start| {
-                let mut x1 = *start;
-                let mut x2 = *start;
-
-                a.steps(&mut x1);
-                b.steps(&mut x1);
+            if check_commute(a, b).is_ok() {
+                pairs.push((i, j));
+            }
+        }
+    }
 
-                b.steps(&mut x2);
-                a.steps(&mut x2);
+    // правила, которые коммутируют со всеми остальными, ничего интересного не дают
+    let mut partners = vec![0; rules.len()];
+    for (i, j) in &pairs {
+        partners[*i] += 1;
+        partners[*j] += 1;
+    }
+    let commute_with_everyone = rules
+        .iter()
+        .zip(&partners)
+        .filter(|(_, count)| **count + 1 == rules.len())
+        .map(|((ni, _), _)| *ni)
+        .collect::<Vec<_>>();
+    println!("commute with everyone: {:?}", commute_with_everyone);
 
-                x1.val == x2.val
-            }) {
-                if first {
-                    println!("---------------------- {}", nia);
-                    first = false;
-                }
-                println!("{} <-> {}", nia, nib);
-            }
+    let mut random = Random::new(25025069121927896);
+    std::fs::create_dir_all("data/img_time2d").unwrap();
+    let mut last = None;
+    for (i, j) in pairs {
+        let ((nia, a), (nib, b)) = (&rules[i], &rules[j]);
+        if commute_with_everyone.contains(nia) || commute_with_everyone.contains(nib) {
+            continue;
+        }
+        if last != Some(i) {
+            println!("---------------------- {}", nia);
+            last = Some(i);
         }
+        // лист двумерного времени одной клетки случайного поля
+        let start = Field::new(random.get() & !(1 << 63), 63);
+        let lattice = TimeLattice::new(a, b, start, 100, 100);
+        assert_eq!(lattice.check_path_independence(b), Ok(()));
+        let name = format!("data/img_time2d/{}_{}.png", nia, nib);
+        lattice.show_cell(31, &name);
+        println!("{} <-> {}: {}", nia, nib, name);
     }
 }
 
 fn main() {
     show_gliders_for_all_rules();
     find_more_gliders(633);
+    find_gliders_in_ether(633);
+    find_wide_gliders(633, 8, 16);
     find_cool_rules();
     find_all_glider_guns_rules();
     find_commute_rules_2d_time();
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn glider_guns() {
-        color_backtrace::install();
-
-        let rule = Rule::num_to_rule(116);
-        assert_eq!(is_this_glider_gun(7, &rule), None);
-
-        let rule = Rule::num_to_rule(160);
-        assert_eq!(is_this_glider_gun(1, &rule), None);
-        assert_eq!(is_this_glider_gun(305, &rule), None);
-        assert_eq!(is_this_glider_gun(437, &rule), None);
-        assert_eq!(is_this_glider_gun(601, &rule), None);
-
-        let rule = Rule::num_to_rule(170);
-        assert_eq!(is_this_glider_gun(16781, &rule), None);
-
-        let rule = Rule::num_to_rule(173);
-        assert_eq!(is_this_glider_gun(7, &rule), None);
-        assert_eq!(is_this_glider_gun(59, &rule), None);
-        assert_eq!(is_this_glider_gun(484, &rule), None);
-        assert_eq!(is_this_glider_gun(9403, &rule), None);
-        assert_eq!(is_this_glider_gun(1250, &rule), Some((5, 33, 2, -1)));
-        assert_eq!(is_this_glider_gun(3813, &rule), Some((5, 33, 2, -1)));
-        assert_eq!(is_this_glider_gun(3911, &rule), Some((5, 33, 2, -1)));
-
-        let rule = Rule::num_to_rule(194);
-        assert_eq!(is_this_glider_gun(2, &rule), Some((2, 1, 2, -1)));
-        assert_eq!(is_this_glider_gun(5, &rule), Some((2, 1, 2, -1)));
-        assert_eq!(is_this_glider_gun(6, &rule), Some((2, 1, 2, -1)));
-        assert_eq!(is_this_glider_gun(12, &rule), Some((2, 1, 2, -1)));
-        assert_eq!(is_this_glider_gun(13, &rule), Some((2, 1, 2, -1)));
-        assert_eq!(is_this_glider_gun(13, &rule), Some((2, 1, 2, -1)));
-        assert_eq!(is_this_glider_gun(18, &rule), Some((2, 1, 2, -1)));
-        assert_eq!(is_this_glider_gun(50, &rule), Some((2, 1, 2, -1)));
-        assert_eq!(is_this_glider_gun(146, &rule), Some((2, 1, 2, -1)));
-        assert_eq!(is_this_glider_gun(1170, &rule), Some((2, 1, 2, -1)));
-        assert_eq!(is_this_glider_gun(9362, &rule), Some((2, 1, 2, -1)));
-
-        let rule = Rule::num_to_rule(207);
-        assert_eq!(is_this_glider_gun(52, &rule), None);
-        assert_eq!(is_this_glider_gun(244, &rule), None);
-        assert_eq!(is_this_glider_gun(3328, &rule), None);
-        assert_eq!(is_this_glider_gun(14068, &rule), None);
-
-        let rule = Rule::num_to_rule(208);
-        assert_eq!(is_this_glider_gun(505, &rule), None);
-        assert_eq!(is_this_glider_gun(862, &rule), None);
-        assert_eq!(is_this_glider_gun(3328, &rule), None);
-        assert_eq!(is_this_glider_gun(14068, &rule), None);
-
-        let rule = Rule::num_to_rule(214);
-        assert_eq!(is_this_glider_gun(32, &rule), None);
-        assert_eq!(is_this_glider_gun(220, &rule), None);
-
-        let rule = Rule::num_to_rule(218);
-        assert_eq!(is_this_glider_gun(2, &rule), Some((2, 1, 2, -1)));
-
-        let rule = Rule::num_to_rule(219);
-        assert_eq!(is_this_glider_gun(99, &rule), Some((2, 1, 2, -1)));
-
-        let rule = Rule::num_to_rule(268);
-        assert_eq!(is_this_glider_gun(3, &rule), Some((2, 1, 2, -1)));
-
-        let rule = Rule::num_to_rule(275);
-        assert_eq!(is_this_glider_gun(81920, &rule), None);
-
-        let rule = Rule::num_to_rule(369);
-        assert_eq!(is_this_glider_gun(6, &rule), None);
-
-        let rule = Rule::num_to_rule(627);
-        assert_eq!(is_this_glider_gun(1699, &rule), Some((5, 33, 2, -1)));
-        assert_eq!(is_this_glider_gun(3237, &rule), Some((5, 33, 2, -1)));
-
-        let rule = Rule::num_to_rule(876);
-        assert_eq!(is_this_glider_gun(2, &rule), Some((1, 1, 1, 1)));
-        assert_eq!(is_this_glider_gun(3, &rule), Some((1, 1, 1, 1)));
-        assert_eq!(is_this_glider_gun(4, &rule), Some((1, 1, 1, 1)));
-        assert_eq!(is_this_glider_gun(10, &rule), Some((1, 1, 1, 1)));
-        assert_eq!(is_this_glider_gun(11, &rule), Some((1, 1, 1, 1)));
-    }
-
-    #[test]
-    fn test1() {
-        color_backtrace::install();
-
-        let rule = Rule::num_to_rule(633);
-        let mut used: HashMap<u64, (u64, u64, i32)> = HashMap::new(); // val, (period, next, offset)
-
-        add_used(Field::new(1, 9), 2, &rule, &mut used);
-        add_used(Field::new(2, 9), 2, &rule, &mut used);
-        add_used(Field::new(4, 9), 2, &rule, &mut used);
-        assert!(!check_used(Field::new(3, 30), 13, &rule, &used));
-        assert!(!check_used(Field::new(5, 30), 13, &rule, &used));
-        assert!(!check_used(Field::new(10, 30), 13, &rule, &used));
-        assert!(!check_used(Field::new(45, 30), 13, &rule, &used));
-        assert!(!check_used(Field::new(765, 30), 13, &rule, &used));
-        assert!(!check_used(Field::new(1215, 30), 28, &rule, &used));
-        assert!(!check_used(Field::new(6125, 30), 13, &rule, &used));
-        assert!(check_used(Field::new(4, 30), 4, &rule, &used));
-        assert!(check_used(Field::new(17, 30), 4, &rule, &used));
-        assert!(check_used(Field::new(23, 30), 4, &rule, &used));
-
-        add_used(Field::new(3, 9), 2, &rule, &mut used);
-        add_used(Field::new(6, 9), 2, &rule, &mut used);
-        add_used(Field::new(12, 9), 2, &rule, &mut used);
-        assert!(!check_used(Field::new(5, 30), 13, &rule, &used));
-        assert!(!check_used(Field::new(10, 30), 13, &rule, &used));
-        assert!(!check_used(Field::new(45, 30), 13, &rule, &used));
-        assert!(!check_used(Field::new(765, 30), 13, &rule, &used));
-        assert!(!check_used(Field::new(1215, 30), 28, &rule, &used));
-        assert!(!check_used(Field::new(6125, 30), 13, &rule, &used));
-        assert!(check_used(Field::new(12, 30), 4, &rule, &used));
-        assert!(check_used(Field::new(19, 30), 4, &rule, &used));
-        assert!(check_used(Field::new(27, 30), 4, &rule, &used));
-
-        add_used(Field::new(5, 12), 4, &rule, &mut used);
-        assert!(!check_used(Field::new(45, 30), 13, &rule, &used));
-        assert!(!check_used(Field::new(765, 30), 13, &rule, &used));
-        assert!(!check_used(Field::new(1215, 30), 28, &rule, &used));
-        assert!(!check_used(Field::new(6125, 30), 13, &rule, &used));
-        assert!(check_used(Field::new(2565, 30), 4, &rule, &used));
-        assert!(check_used(Field::new(2805, 30), 4, &rule, &used));
-        assert!(check_used(Field::new(10405, 30), 4, &rule, &used));
-        assert!(check_used(Field::new(89775, 30), 4, &rule, &used));
-
-        add_used(Field::new(10, 12), 2, &rule, &mut used);
-        assert!(!check_used(Field::new(45, 30), 13, &rule, &used));
-        assert!(!check_used(Field::new(765, 30), 13, &rule, &used));
-        assert!(!check_used(Field::new(1215, 30), 28, &rule, &used));
-        assert!(!check_used(Field::new(6125, 30), 13, &rule, &used));
-        assert!(check_used(Field::new(490, 30), 2, &rule, &used));
-        assert!(check_used(Field::new(650, 30), 2, &rule, &used));
-        assert!(check_used(Field::new(3850, 30), 2, &rule, &used));
-
-        add_used(Field::new(45, 15), 13, &rule, &mut used);
-        assert!(!check_used(Field::new(765, 30), 13, &rule, &used));
-        assert!(!check_used(Field::new(1215, 30), 28, &rule, &used));
-        assert!(!check_used(Field::new(6125, 30), 13, &rule, &used));
-        assert!(check_used(Field::new(23085, 30), 13, &rule, &used));
-        assert!(check_used(Field::new(23275, 30), 13, &rule, &used));
-        assert!(check_used(Field::new(92255, 30), 13, &rule, &used));
-    }
-
-    #[test]
-    fn test() {
-        color_backtrace::install();
-
-        let rule1 = Rule::new([PAT0, PAT1, PAT2, PAT3, PAT4, PAT5, PAT7, PAT6]);
-
-        let mut x = Field::new(0b001_110_111, 63);
-        rule1.replace_all(&mut x);
-        assert_eq!(x, Field::new(0b001_111_110, 63));
-
-        let rule2 = Rule::new([PAT0, PAT2, PAT1, PAT4, PAT3, PAT6, PAT5, PAT7]);
-        let mut x = Field::new(0b001_010_011_100_101_110_111, 63);
-        rule2.replace_all(&mut x);
-        assert_eq!(x, Field::new(0b010_001_100_011_110_101_111, 63));
-
-        let x = Field::new(repeat_bit(0b01, 2), 63);
-        assert_eq!(x, x.rotate_right(1).rotate_left(1));
-        assert_eq!(x, x.rotate_left(1).rotate_right(1));
-
-        let mut random = Random::new(25025069121927896);
-        let start = Field::new(random.get() & !(ALL_ONES << 45), 45);
-        for i in 0..100 {
-            assert_eq!(start.rotate_left(i), {
-                let mut x = start;
-                for _ in 0..i {
-                    x = x.rotate_left(1);
-                }
-                x
-            });
-            assert_eq!(start.rotate_right(i), {
-                let mut x = start;
-                for _ in 0..i {
-                    x = x.rotate_right(1);
-                }
-                x
-            });
-        }
-
-        // assert_eq!(Field::new(0b0101, 4).rotate_left(0), Field::new(0b0101, 4));
-        // assert_eq!(Field::new(0b0101, 4).rotate_left(1), Field::new(0b1010, 4));
-        // assert_eq!(Field::new(0b0101, 4).rotate_left(2), Field::new(0b0101, 4));
-        // assert_eq!(Field::new(0b0101, 4).rotate_left(3), Field::new(0b1010, 4));
-        // assert_eq!(Field::new(0b0101, 4).rotate_left(4), Field::new(0b0101, 4));
-
-        // assert_eq!(Field::new(0b1110, 4).rotate_left(0), Field::new(0b1110, 4));
-        // assert_eq!(Field::new(0b1110, 4).rotate_left(1), Field::new(0b1101, 4));
-        // assert_eq!(Field::new(0b1110, 4).rotate_left(2), Field::new(0b1011, 4));
-        // assert_eq!(Field::new(0b1110, 4).rotate_left(3), Field::new(0b0111, 4));
-        // assert_eq!(Field::new(0b1110, 4).rotate_left(4), Field::new(0b1110, 4));
-
-        let rule = Rule::num_to_rule(633);
-        assert!(check_reach_everything(Field::new(5, 30), &rule, 4));
-        assert!(check_reach_everything(Field::new(10, 30), &rule, 2));
-        assert!(check_reach_everything(Field::new(45, 30), &rule, 13));
-        assert!(!check_reach_everything(Field::new(165, 30), &rule, 4));
-        assert!(!check_reach_everything(Field::new(175, 30), &rule, 4));
-        assert!(!check_reach_everything(Field::new(325, 30), &rule, 4));
-        assert!(!check_reach_everything(Field::new(490, 30), &rule, 2));
-        assert!(!check_reach_everything(Field::new(650, 30), &rule, 2));
-        assert!(check_reach_everything(Field::new(765, 30), &rule, 13));
-        assert!(!check_reach_everything(Field::new(965, 30), &rule, 4));
-        assert!(check_reach_everything(Field::new(1215, 30), &rule, 28));
-        assert!(!check_reach_everything(Field::new(1285, 30), &rule, 4));
-        assert!(!check_reach_everything(Field::new(2565, 30), &rule, 4));
-        assert!(!check_reach_everything(Field::new(5610, 30), &rule, 2));
-        assert!(check_reach_everything(Field::new(6125, 30), &rule, 13));
-        assert!(check_reach_everything(Field::new(11755, 30), &rule, 43));
-        assert!(check_reach_everything(Field::new(13805, 30), &rule, 43));
-        assert!(!check_reach_everything(Field::new(23275, 30), &rule, 13));
-        assert!(!check_reach_everything(Field::new(390650, 30), &rule, 13));
-    }
-
-    #[test]
-    fn find_pattern_start_test() {
-        color_backtrace::install();
-
-        assert_eq!(find_pattern_start(Field::new(0b111_100_000_000, 12)), -2);
-
-        assert_eq!(
-            find_pattern_start(Field::new(0b001_101_100, 10 * 3).rotate_left(3 * 3)),
-            3
-        );
-        assert_eq!(
-            find_pattern_start(Field::new(0b001_101_100, 10 * 3).rotate_left(3 * 2)),
-            2
-        );
-        assert_eq!(
-            find_pattern_start(Field::new(0b001_101_100, 10 * 3).rotate_left(3)),
-            1
-        );
-        assert_eq!(
-            find_pattern_start(Field::new(0b001_101_100, 10 * 3).rotate_right(3)),
-            -1
-        );
-        assert_eq!(
-            find_pattern_start(Field::new(0b001_101_100, 10 * 3).rotate_right(3 * 2)),
-            -2
-        );
-        assert_eq!(
-            find_pattern_start(Field::new(0b001_101_100, 10 * 3).rotate_right(3 * 3)),
-            -3
-        );
-
-        assert_eq!(
-            find_pattern_start(Field::new(0b001_101_100, 4 * 3).rotate_left(3)),
-            1
-        );
-        assert_eq!(
-            find_pattern_start(Field::new(0b001_101_100, 4 * 3).rotate_right(3)),
-            -1
-        );
-    }
-}
+// рисовать ли повреждения для каждого правила в таблице: по 300 фаз на правило
+const SHOW_DAMAGE: bool = false;
 
 const BEFORE: &str = "
 <style>
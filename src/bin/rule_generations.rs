@@ -2,6 +2,8 @@ use rand::prelude::*;
 use std::fmt;
 use std::io::prelude::*;
 use std::process::Command;
+use time_2d_inversible_automata::block::commute::check_commute;
+use time_2d_inversible_automata::block::BlockRule;
 use time_2d_inversible_automata::*;
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
@@ -62,6 +64,15 @@ impl Rule {
         self.step(array, false);
     }
 
+    /// The same rule as a block permutation. `Block` stores the first cell in the high
+    /// bit, `BlockRule` in the low bit, so indices and values are mirrored.
+    fn to_block_rule(&self) -> BlockRule<2> {
+        let p = (0..4)
+            .map(|i| self.0[Block(i).mirror().0 as usize].mirror().0 as usize)
+            .collect::<Vec<_>>();
+        BlockRule::from_permutation(&p)
+    }
+
     fn full_step_like_invert(&self, array: &mut Vec<bool>) {
         self.step(array, false);
         self.step(array, true);
@@ -188,26 +199,6 @@ impl Rule {
 
 /// Rule relationship with others
 impl Rule {
-    /// Needed for 2D time
-    fn is_commute_with(
-        &self,
-        other: &Rule,
-        array: &[bool],
-        copy1: &mut Vec<bool>,
-        copy2: &mut Vec<bool>,
-    ) -> bool {
-        copy_arr(array, copy1);
-        copy_arr(array, copy2);
-
-        self.full_step(copy1);
-        other.full_step(copy1);
-
-        other.full_step(copy2);
-        self.full_step(copy2);
-
-        copy1 == copy2
-    }
-
     /// Other can be invert rule for self
     fn is_time_invert_by(&self, other: &Rule, array: &[bool], copy1: &mut Vec<bool>) -> bool {
         copy_arr(array, copy1);
@@ -419,7 +410,6 @@ fn main() {
     let mut rng = rand::thread_rng();
     let array = (0..10000).map(|_| rng.gen::<bool>()).collect::<Vec<_>>();
     let mut copy1 = array.clone();
-    let mut copy2 = array.clone();
 
     let rules = get_rules();
 
@@ -539,7 +529,7 @@ fn main() {
                 .skip(ni)
                 .filter(|(nj, _)| show.filter(*nj))
             {
-                if i.is_commute_with(j, &array, &mut copy1, &mut copy2) && ni != nj {
+                if ni != nj && check_commute(&i.to_block_rule(), &j.to_block_rule()).is_ok() {
                     write!(file, "{} -> {};", ni, nj).unwrap();
                 }
             }
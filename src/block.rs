@@ -0,0 +1,568 @@
+//! Битовый движок для обратимых блочных автоматов с произвольным размером окна `N`.
+//!
+//! Поле хранится в одном `u64`, по одному биту на клетку. Правило это перестановка `2^N`
+//! состояний блока из `N` клеток. Полный шаг состоит из `N` фаз: на фазе `i` правило
+//! применяется к блокам, сдвинутым на `i` клеток. Поддерживаются окна от 2 до 5.
+
+use crate::{draw_image, repeat_bit};
+use backend::Backend;
+use permutation_string::*;
+use std::fmt;
+
+pub mod backend;
+pub mod batch;
+pub mod boundary;
+pub mod classes;
+pub mod collisions;
+pub mod commute;
+pub mod complexity;
+pub mod cycles;
+pub mod damage;
+pub mod ether;
+pub mod gliders;
+pub mod infinite;
+pub mod invariants;
+pub mod particles;
+pub mod schedule;
+pub mod spaceships;
+pub mod symmetry;
+pub mod time2d;
+pub mod wide;
+
+/// Максимальное количество состояний блока, `2^5`.
+pub const MAX_STATES: usize = 32;
+
+/// Маска из `bits` младших единиц, работает и для `bits == 64`.
+pub const fn low_mask(bits: u32) -> u64 {
+    if bits >= 64 {
+        !0
+    } else {
+        (1 << bits) - 1
+    }
+}
+
+/// Заменяет все блоки `pat1` на блоки `pat2`, остальные блоки обнуляются.
+pub const fn replace<const N: usize>(x: u64, pat1: u64, pat2: u64) -> u64 {
+    let bits_first = repeat_bit(1, N as u8);
+    let x1 = !(x ^ pat1);
+    let mut x2 = x1 & bits_first;
+    let mut i = 1;
+    while i < N {
+        x2 &= x1 >> i;
+        i += 1;
+    }
+    let mut x3 = x2;
+    let mut i = 1;
+    while i < N {
+        x3 |= x2 << i;
+        i += 1;
+    }
+    x3 & pat2
+}
+
+/// Перестановка `2^N` состояний блока: `i`-й элемент это паттерн, в который переходит блок `i`.
+/// Второе поле это таблица переходов для [`Backend::Table`], она однозначно задаётся паттернами.
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct BlockRule<const N: usize>([u64; MAX_STATES], [u8; 256]);
+
+/// Циклическое поле из `size` клеток, `size` кратен `N` и не больше [`BlockField::MAX_SIZE`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct BlockField<const N: usize> {
+    val: u64,
+    size: u32,
+}
+
+impl<const N: usize> BlockRule<N> {
+    pub const STATES: usize = 1 << N;
+
+    pub const RULE0: Self = {
+        let mut pats = [0; MAX_STATES];
+        let mut i = 0;
+        while i < Self::STATES {
+            pats[i] = repeat_bit(i as u64, N as u8);
+            i += 1;
+        }
+        Self(pats, backend::table::<N>(&pats))
+    };
+
+    /// Создаёт правило из паттернов, в которые переходит каждый блок.
+    pub fn new(x: &[u64]) -> Self {
+        assert_eq!(x.len(), Self::STATES);
+        let mut pats = [0; MAX_STATES];
+        pats[..Self::STATES].copy_from_slice(x);
+        Self(pats, backend::table::<N>(&pats))
+    }
+
+    /// Создаёт правило из перестановки: блок `i` переходит в блок `p[i]`.
+    pub fn from_permutation(p: &[usize]) -> Self {
+        assert_eq!(p.len(), Self::STATES);
+        let mut pats = [0; MAX_STATES];
+        for (to, &i) in pats.iter_mut().zip(p) {
+            *to = Self::RULE0.0[i];
+        }
+        Self(pats, backend::table::<N>(&pats))
+    }
+
+    /// Перестановка, задающая правило.
+    pub fn permutation(&self) -> Vec<usize> {
+        self.0[..Self::STATES]
+            .iter()
+            .map(|x| (x & low_mask(N as u32)) as usize)
+            .collect()
+    }
+
+    fn num_to_permutation(ni: u64) -> Vec<usize> {
+        let p = PermutationInt::new(ni, Self::STATES);
+        let p = PermutationIndex::try_from(p).unwrap();
+        let p = PermutationArray::try_from(p).unwrap();
+        p.0
+    }
+
+    pub fn num_to_rule(ni: u64) -> Self {
+        Self::from_permutation(&Self::num_to_permutation(ni))
+    }
+
+    pub fn num_to_rule_name(ni: u64) -> String {
+        Self::num_to_permutation(ni)
+            .into_iter()
+            .map(|i| char::from_digit(i as u32, 36).unwrap())
+            .collect()
+    }
+
+    /// Применяет правило ко всем блокам слова, блоки выровнены по младшему биту. Способ
+    /// вычисления выбирается через [`backend::backend`], результат от него не зависит.
+    pub fn replace_word(&self, x: u64) -> u64 {
+        match backend::backend::<N>() {
+            Backend::Mask => self.replace_word_mask(x),
+            Backend::Table => self.replace_word_table(x),
+        }
+    }
+
+    /// [`BlockRule::replace_word`] через маски: по одному [`replace`] на каждое состояние блока.
+    pub fn replace_word_mask(&self, x: u64) -> u64 {
+        let mut result = 0;
+        for i in 0..Self::STATES {
+            result |= replace::<N>(x, Self::RULE0.0[i], self.0[i]);
+        }
+        result
+    }
+
+    /// [`BlockRule::replace_word`] через таблицу: по одному обращению на каждые 8 бит.
+    pub fn replace_word_table(&self, x: u64) -> u64 {
+        let chunk = backend::chunk_bits::<N>();
+        let mut result = 0;
+        let mut shift = 0;
+        while shift < BlockField::<N>::MAX_SIZE {
+            result |= (self.1[((x >> shift) & low_mask(chunk)) as usize] as u64) << shift;
+            shift += chunk;
+        }
+        result & low_mask(BlockField::<N>::MAX_SIZE)
+    }
+
+    pub fn replace_all(&self, x: &mut BlockField<N>) {
+        x.val = self.replace_word(x.val) & low_mask(x.size);
+    }
+
+    /// Одна фаза: правило применяется к блокам, сдвинутым на `phase` клеток.
+    pub fn step<F: Cells<N>>(&self, x: &mut F, phase: u32) {
+        x.apply_phase(self, phase);
+    }
+
+    /// Полный шаг: все `N` фаз по порядку.
+    pub fn steps<F: Cells<N>>(&self, x: &mut F) {
+        x.apply_steps(self);
+    }
+
+    pub fn steps_count<F: Cells<N>>(&self, x: &mut F, count: u64) {
+        for _ in 0..count {
+            self.steps(x);
+        }
+    }
+
+    /// Обратное правило: блок, в который переходит `i`, переходит обратно в `i`.
+    pub fn inverse(&self) -> Self {
+        let mut p = vec![0; Self::STATES];
+        for (i, to) in self.permutation().into_iter().enumerate() {
+            p[to] = i;
+        }
+        Self::from_permutation(&p)
+    }
+
+    /// Отменяет полный шаг: фазы `N - 1, ..., 0` с обратным правилом.
+    pub fn steps_back<F: Cells<N>>(&self, x: &mut F) {
+        let inverse = self.inverse();
+        for phase in (0..N as u32).rev() {
+            inverse.step(x, phase);
+        }
+    }
+
+    pub fn steps_back_count<F: Cells<N>>(&self, x: &mut F, count: u64) {
+        let inverse = self.inverse();
+        for _ in 0..count {
+            for phase in (0..N as u32).rev() {
+                inverse.step(x, phase);
+            }
+        }
+    }
+}
+
+impl<const N: usize> fmt::Debug for BlockRule<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("BlockRule")
+            .field(&self.permutation())
+            .finish()
+    }
+}
+
+/// Эволюция поля, с которой работают все анализы: полный шаг из нескольких фаз. Для
+/// [`BlockRule`] это `N` фаз со смещениями `0, 1, ..., N - 1`, для [`schedule::Schedule`]
+/// произвольный список фаз.
+pub trait Evolution<const N: usize> {
+    /// Количество фаз в полном шаге.
+    fn phases(&self) -> u32;
+
+    /// Смещение блоков на фазе с номером `phase` по модулю [`Evolution::phases`].
+    fn offset(&self, phase: u32) -> u32;
+
+    /// Применяет фазу с номером `phase` по модулю [`Evolution::phases`].
+    fn phase<F: Cells<N>>(&self, x: &mut F, phase: u32);
+
+    /// Полный шаг: все фазы по порядку.
+    fn steps<F: Cells<N>>(&self, x: &mut F) {
+        for phase in 0..self.phases() {
+            self.phase(x, phase);
+        }
+    }
+
+    fn steps_count<F: Cells<N>>(&self, x: &mut F, count: u64) {
+        for _ in 0..count {
+            self.steps(x);
+        }
+    }
+
+    /// Рисует `steps` фаз эволюции в `data/img_{img_name}/` и возвращает путь относительно `data/`.
+    fn show_field(
+        &self,
+        mut x: BlockField<N>,
+        steps: u64,
+        dupl: u64,
+        ni: u64,
+        img_name: &str,
+    ) -> String {
+        let x_start = x.val;
+        let mut result = vec![x.to_bools(dupl)];
+        for _ in 0..steps / self.phases() as u64 {
+            for phase in 0..self.phases() {
+                self.phase(&mut x, phase);
+                result.push(x.to_bools(dupl));
+            }
+        }
+        std::fs::create_dir_all(format!("data/img_{}", img_name)).unwrap();
+        let name = format!(
+            "img_{}/{}_{}_{}_{}_{}.png",
+            img_name, x_start, x.size, steps, dupl, ni
+        );
+        draw_image(&format!("data/{}", name), result);
+        name
+    }
+}
+
+impl<const N: usize> Evolution<N> for BlockRule<N> {
+    fn phases(&self) -> u32 {
+        N as u32
+    }
+
+    fn offset(&self, phase: u32) -> u32 {
+        phase % N as u32
+    }
+
+    fn phase<F: Cells<N>>(&self, x: &mut F, phase: u32) {
+        self.step(x, phase);
+    }
+
+    fn steps<F: Cells<N>>(&self, x: &mut F) {
+        x.apply_steps(self);
+    }
+}
+
+/// Поле, к которому можно применять блочное правило с окном `N`.
+pub trait Cells<const N: usize> {
+    /// Применяет правило к блокам, сдвинутым на `phase` клеток.
+    fn apply_phase(&mut self, rule: &BlockRule<N>, phase: u32);
+
+    /// Полный шаг: все `N` фаз по порядку.
+    fn apply_steps(&mut self, rule: &BlockRule<N>) {
+        for phase in 0..N as u32 {
+            self.apply_phase(rule, phase);
+        }
+    }
+}
+
+impl<const N: usize> Cells<N> for BlockField<N> {
+    fn apply_phase(&mut self, rule: &BlockRule<N>, phase: u32) {
+        *self = self.rotate_right(phase);
+        rule.replace_all(self);
+        *self = self.rotate_left(phase);
+    }
+
+    fn apply_steps(&mut self, rule: &BlockRule<N>) {
+        rule.replace_all(self);
+        for _ in 1..N {
+            *self = self.rotate_right(1);
+            rule.replace_all(self);
+        }
+        *self = self.rotate_left(N as u32 - 1);
+    }
+}
+
+impl<const N: usize> BlockField<N> {
+    /// Наибольший размер поля, в который помещается целое число блоков.
+    pub const MAX_SIZE: u32 = (64 / N * N) as u32;
+
+    pub fn new(val: u64, size: u32) -> Self {
+        assert!((2..=5).contains(&N));
+        assert!(size.is_multiple_of(N as u32));
+        assert!(size <= 64);
+        assert!(val == (val & low_mask(size)));
+        Self { val, size }
+    }
+
+    pub fn val(&self) -> u64 {
+        self.val
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn rotate_left(mut self, mut count: u32) -> Self {
+        count %= self.size;
+        if count != 0 {
+            self.val =
+                ((self.val << count) | (self.val >> (self.size - count))) & low_mask(self.size);
+        }
+        self
+    }
+
+    pub fn rotate_right(mut self, mut count: u32) -> Self {
+        count %= self.size;
+        if count != 0 {
+            self.val =
+                ((self.val >> count) | (self.val << (self.size - count))) & low_mask(self.size);
+        }
+        self
+    }
+
+    pub fn rotate(self, count: i32) -> Self {
+        if count < 0 {
+            self.rotate_left((-count) as u32)
+        } else {
+            self.rotate_right(count as u32)
+        }
+    }
+
+    pub fn to_bools(&self, dupl: u64) -> Vec<bool> {
+        let size = self.size as usize;
+        let mut result = vec![false; size];
+        for i in 0..size {
+            result[size - i - 1] = ((self.val >> i) & 1) != 0;
+        }
+        let mut result2 = Vec::new();
+        for _ in 0..dupl {
+            result2.extend(result.iter());
+        }
+        result2
+    }
+
+    /// Количество занятых бит, округлённое вверх до целого блока.
+    pub fn occupied_size(&self) -> u32 {
+        occupied_size_n(self.val, N as u32)
+    }
+
+    /// Минимальное число среди всех поворотов поля на целое число блоков.
+    pub fn minimize(mut self) -> Self {
+        let mut my_min = self.val;
+        for i in 0..=self.size / N as u32 {
+            my_min = my_min.min(self.rotate_left(i * N as u32).val);
+        }
+        self.val = my_min;
+        self
+    }
+
+    pub fn centralize(self) -> Self {
+        let mut offset = (self.size - self.occupied_size()) / 2;
+        offset -= offset % N as u32;
+        self.rotate_left(offset)
+    }
+
+    pub fn index(&self, pos: u32) -> bool {
+        ((self.val >> pos) & 1) != 0
+    }
+}
+
+/// Количество занятых бит, округлённое вверх до блока из `n` бит.
+pub fn occupied_size_n(mut x: u64, n: u32) -> u32 {
+    let mut count = 0;
+    while x != 0 {
+        x = x.checked_shr(n).unwrap_or(0);
+        count += n;
+    }
+    count
+}
+
+/// Количество занятых бит.
+pub fn occupied_size(x: u64) -> u32 {
+    occupied_size_n(x, 1)
+}
+
+/// Медленная фаза по клеткам, с которой тесты сравнивают быстрые поля. Блоки идут с
+/// `phase - N`, клетки за краями поля даёт `ghost` по старым значениям и позиции, блок
+/// заменяется на `replace(block, partial)`, где `partial` значит, что блок выходит за край. В
+/// поле записываются только клетки внутри, так что кольцо получается из `ghost`, который
+/// заворачивает позицию.
+#[cfg(test)]
+pub(crate) fn naive_phase<const N: usize>(
+    cells: &mut [bool],
+    phase: usize,
+    ghost: impl Fn(&[bool], i64) -> bool,
+    replace: impl Fn(usize, bool) -> usize,
+) {
+    let size = cells.len() as i64;
+    let old = cells.to_vec();
+    let get = |pos: i64| {
+        if (0..size).contains(&pos) {
+            old[pos as usize]
+        } else {
+            ghost(&old, pos)
+        }
+    };
+    let n = N as i64;
+    let phase = phase as i64 % n;
+    let mut start = if phase == 0 { 0 } else { phase - n };
+    while start < size {
+        let partial = start < 0 || start + n > size;
+        let block = (0..n).fold(0, |acc, k| acc | ((get(start + k) as usize) << k));
+        let to = replace(block, partial);
+        for k in 0..n {
+            if (0..size).contains(&(start + k)) {
+                cells[(start + k) as usize] = (to >> k) & 1 != 0;
+            }
+        }
+        start += n;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Random;
+
+    #[test]
+    fn replace_matches_window_2() {
+        // старая реализация из fast_periods_gliders.rs
+        fn replace2(x: u64, pat1: u64, pat2: u64) -> u64 {
+            const BITS_ODD: u64 = repeat_bit(0b01, 2);
+            let x1 = !(x ^ pat1);
+            let x2 = x1 & (x1 >> 1) & BITS_ODD;
+            let x3 = x2 | (x2 << 1);
+            x3 & pat2
+        }
+
+        let mut random = Random::new(25025069121927896);
+        for _ in 0..100 {
+            let x = random.get();
+            for from in 0..4 {
+                for to in 0..4 {
+                    let (from, to) = (repeat_bit(from, 2), repeat_bit(to, 2));
+                    assert_eq!(replace::<2>(x, from, to), replace2(x, from, to));
+                }
+            }
+        }
+    }
+
+    fn check_rotations<const N: usize>(size: u32) {
+        let mut random = Random::new(25025069121927896);
+        let start = BlockField::<N>::new(random.get() & low_mask(size), size);
+        for i in 0..100 {
+            assert_eq!(start.rotate_left(i), {
+                let mut x = start;
+                for _ in 0..i {
+                    x = x.rotate_left(1);
+                }
+                x
+            });
+            assert_eq!(start.rotate_right(i), {
+                let mut x = start;
+                for _ in 0..i {
+                    x = x.rotate_right(1);
+                }
+                x
+            });
+            assert_eq!(start, start.rotate_left(i).rotate_right(i));
+        }
+    }
+
+    #[test]
+    fn rotations() {
+        check_rotations::<2>(64);
+        check_rotations::<3>(63);
+        check_rotations::<4>(64);
+        check_rotations::<4>(36);
+        check_rotations::<5>(60);
+    }
+
+    fn check_step_is_permutation<const N: usize>(ni: u64) {
+        let rule = BlockRule::<N>::num_to_rule(ni);
+        let size = BlockField::<N>::MAX_SIZE;
+        let mut random = Random::new(25025069121927896);
+        for _ in 0..100 {
+            let mut x = BlockField::<N>::new(random.get() & low_mask(size), size);
+            let mut blocks = (0..size / N as u32)
+                .map(|i| (x.val() >> (i * N as u32)) & low_mask(N as u32))
+                .collect::<Vec<_>>();
+            rule.replace_all(&mut x);
+            for block in &mut blocks {
+                *block = rule.permutation()[*block as usize] as u64;
+            }
+            let expected = blocks
+                .iter()
+                .enumerate()
+                .fold(0, |acc, (i, b)| acc | (b << (i * N)));
+            assert_eq!(x.val(), expected);
+        }
+    }
+
+    #[test]
+    fn replace_all_applies_permutation() {
+        check_step_is_permutation::<2>(13);
+        check_step_is_permutation::<3>(633);
+        check_step_is_permutation::<4>(123_456_789);
+    }
+
+    fn check_steps_back<const N: usize>(ni: u64, size: u32) {
+        let rule = BlockRule::<N>::num_to_rule(ni);
+        let mut random = Random::new(25025069121927896);
+        assert_eq!(rule.inverse().inverse(), rule);
+        for _ in 0..100 {
+            let start = BlockField::<N>::new(random.get() & low_mask(size), size);
+            let mut x = start;
+            rule.steps_count(&mut x, 10);
+            rule.steps_back_count(&mut x, 10);
+            assert_eq!(x, start);
+
+            let mut x = start;
+            rule.steps_back(&mut x);
+            rule.steps(&mut x);
+            assert_eq!(x, start);
+        }
+    }
+
+    #[test]
+    fn steps_back() {
+        check_steps_back::<2>(13, 64);
+        check_steps_back::<3>(633, 63);
+        check_steps_back::<3>(20000, 30);
+        check_steps_back::<4>(123_456_789, 64);
+        check_steps_back::<5>(987_654_321_987, 60);
+    }
+}
@@ -0,0 +1,118 @@
+//! Выбор способа применения правила к слову.
+//!
+//! [`Backend::Mask`] делает по одному [`replace`] на каждое из `2^N` состояний блока, поэтому
+//! дорожает с ростом окна. [`Backend::Table`] разбивает слово на куски по 8 бит (целое число
+//! блоков) и берёт результат из таблицы на 256 элементов. Оба делают одну и ту же работу для
+//! любого правила, так что какой быстрее, зависит только от окна и машины. Поэтому выбор
+//! делается один раз для каждого `N` замером при первом вызове, а не для каждого правила.
+
+use super::*;
+use crate::Random;
+use std::hint::black_box;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Backend {
+    Mask,
+    Table,
+}
+
+// 0 = ещё не выбран, иначе `Backend as u8 + 1`, индекс это `N`
+static BACKENDS: [AtomicU8; 6] = [const { AtomicU8::new(0) }; 6];
+
+/// Количество бит в одном куске таблицы: целое число блоков, не больше 8.
+pub const fn chunk_bits<const N: usize>() -> u32 {
+    (8 / N * N) as u32
+}
+
+/// Таблица переходов для куска из [`chunk_bits`] бит.
+pub const fn table<const N: usize>(pats: &[u64; MAX_STATES]) -> [u8; 256] {
+    let blocks = 8 / N;
+    let mask = low_mask(N as u32);
+    let mut result = [0; 256];
+    let mut i = 0;
+    while i < 1 << chunk_bits::<N>() {
+        let mut to = 0;
+        let mut j = 0;
+        while j < blocks {
+            let block = (i as u64 >> (j * N)) & mask;
+            to |= (pats[block as usize] & mask) << (j * N);
+            j += 1;
+        }
+        result[i] = to as u8;
+        i += 1;
+    }
+    result
+}
+
+/// Текущий способ для окна `N`. При первом вызове выбирается замером [`BlockRule::benchmark`].
+pub fn backend<const N: usize>() -> Backend {
+    match BACKENDS[N].load(Ordering::Relaxed) {
+        1 => Backend::Mask,
+        2 => Backend::Table,
+        _ => {
+            let rule = BlockRule::<N>::from_permutation(
+                &(0..BlockRule::<N>::STATES).rev().collect::<Vec<_>>(),
+            );
+            let result = rule
+                .benchmark(10000)
+                .into_iter()
+                .min_by_key(|(_, time)| *time)
+                .unwrap()
+                .0;
+            set_backend::<N>(result);
+            result
+        }
+    }
+}
+
+/// Принудительно выбирает способ для окна `N`.
+pub fn set_backend<const N: usize>(backend: Backend) {
+    BACKENDS[N].store(backend as u8 + 1, Ordering::Relaxed);
+}
+
+impl<const N: usize> BlockRule<N> {
+    /// Время `count` применений правила к случайным словам каждым способом.
+    pub fn benchmark(&self, count: u64) -> [(Backend, Duration); 2] {
+        let mut random = Random::new(25025069121927896);
+        let words = (0..count).map(|_| random.get()).collect::<Vec<_>>();
+        let measure = |f: &dyn Fn(u64) -> u64| {
+            let start = Instant::now();
+            for x in &words {
+                black_box(f(black_box(*x)));
+            }
+            start.elapsed()
+        };
+        [
+            (Backend::Mask, measure(&|x| self.replace_word_mask(x))),
+            (Backend::Table, measure(&|x| self.replace_word_table(x))),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_same<const N: usize>(ni: u64) {
+        let rule = BlockRule::<N>::num_to_rule(ni);
+        let mut random = Random::new(25025069121927896);
+        for _ in 0..1000 {
+            let x = random.get();
+            assert_eq!(rule.replace_word_mask(x), rule.replace_word_table(x));
+        }
+        assert_eq!(rule.replace_word_mask(!0), rule.replace_word_table(!0));
+    }
+
+    #[test]
+    fn table_same_as_mask() {
+        check_same::<2>(13);
+        check_same::<3>(0);
+        check_same::<3>(633);
+        check_same::<3>(40319);
+        check_same::<4>(123_456_789);
+        check_same::<5>(987_654_321_987);
+        check_same::<5>(0);
+    }
+}
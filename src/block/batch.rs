@@ -0,0 +1,313 @@
+//! Одновременная эволюция 64 независимых полей.
+//!
+//! Поле хранится транспонированным: `cells[i]` это клетка `i` во всех 64 полях (дорожках),
+//! бит `j` относится к дорожке `j`. Правило применяется к блоку сразу во всех дорожках
+//! булевыми операциями над его клетками, поэтому в разных дорожках могут быть разные правила.
+
+use super::gliders::Ring;
+use super::*;
+use std::collections::BTreeSet;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// По правилу на каждую из 64 дорожек.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct BatchRule<const N: usize> {
+    // masks[k][s]: дорожки, в которых блок `s` переходит в блок с единицей в клетке `k`
+    masks: [[u64; MAX_STATES]; 5],
+}
+
+/// 64 циклических поля одинакового размера `size`, размер не ограничен одним словом.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct BatchField<const N: usize> {
+    cells: Vec<u64>,
+    size: u32,
+}
+
+impl<const N: usize> BatchRule<N> {
+    /// Дорожка `i` использует правило `rules[i]`, дорожки после `rules.len()` не меняются.
+    pub fn new(rules: &[BlockRule<N>]) -> Self {
+        assert!(rules.len() <= 64);
+        let mut masks = [[0; MAX_STATES]; 5];
+        for lane in 0..64 {
+            let p = match rules.get(lane) {
+                Some(rule) => rule.permutation(),
+                None => BlockRule::<N>::RULE0.permutation(),
+            };
+            for (s, to) in p.into_iter().enumerate() {
+                for (k, mask) in masks.iter_mut().enumerate().take(N) {
+                    mask[s] |= (((to >> k) & 1) as u64) << lane;
+                }
+            }
+        }
+        Self { masks }
+    }
+
+    /// Одно правило во всех дорожках. Маски это целые слова, так что строится быстро и годится
+    /// для [`Cells`].
+    pub fn from_rule(rule: &BlockRule<N>) -> Self {
+        let mut masks = [[0; MAX_STATES]; 5];
+        for (s, to) in rule.permutation().into_iter().enumerate() {
+            for (k, mask) in masks.iter_mut().enumerate().take(N) {
+                mask[s] = 0u64.wrapping_sub(((to >> k) & 1) as u64);
+            }
+        }
+        Self { masks }
+    }
+
+    fn replace_block(&self, input: &[u64; 5]) -> [u64; 5] {
+        // minterms[s]: дорожки, в которых блок равен `s`
+        let mut minterms = [0; MAX_STATES];
+        minterms[0] = !0;
+        for (k, cell) in input.iter().enumerate().take(N) {
+            for s in (0..1 << k).rev() {
+                minterms[s | (1 << k)] = minterms[s] & cell;
+                minterms[s] &= !cell;
+            }
+        }
+        let mut result = [0; 5];
+        for (k, to) in result.iter_mut().enumerate().take(N) {
+            for (s, minterm) in minterms.iter().enumerate().take(BlockRule::<N>::STATES) {
+                *to |= minterm & self.masks[k][s];
+            }
+        }
+        result
+    }
+
+    /// Одна фаза во всех дорожках.
+    pub fn step(&self, x: &mut BatchField<N>, phase: u32) {
+        let size = x.size as usize;
+        let phase = (phase % N as u32) as usize;
+        for start in (phase..size + phase).step_by(N) {
+            let mut input = [0; 5];
+            for (k, cell) in input.iter_mut().enumerate().take(N) {
+                *cell = x.cells[(start + k) % size];
+            }
+            let output = self.replace_block(&input);
+            for (k, cell) in output.iter().enumerate().take(N) {
+                x.cells[(start + k) % size] = *cell;
+            }
+        }
+    }
+
+    /// Полный шаг: все `N` фаз по порядку.
+    pub fn steps(&self, x: &mut BatchField<N>) {
+        for phase in 0..N as u32 {
+            self.step(x, phase);
+        }
+    }
+
+    pub fn steps_count(&self, x: &mut BatchField<N>, count: u64) {
+        for _ in 0..count {
+            self.steps(x);
+        }
+    }
+}
+
+impl<const N: usize> BatchField<N> {
+    /// 64 пустых поля из `size` клеток.
+    pub fn new(size: u32) -> Self {
+        assert!((2..=5).contains(&N));
+        assert!(size != 0 && size.is_multiple_of(N as u32));
+        Self {
+            cells: vec![0; size as usize],
+            size,
+        }
+    }
+
+    /// Дорожка `i` это поле `fields[i]`, остальные дорожки пустые.
+    pub fn from_fields(fields: &[BlockField<N>]) -> Self {
+        assert!(!fields.is_empty() && fields.len() <= 64);
+        let mut result = Self::new(fields[0].size());
+        for (lane, x) in fields.iter().enumerate() {
+            assert_eq!(x.size(), result.size);
+            for (i, cell) in result.cells.iter_mut().enumerate() {
+                *cell |= ((x.val() >> i) & 1) << lane;
+            }
+        }
+        result
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Поле в дорожке `lane`, если оно помещается в [`BlockField`].
+    pub fn lane(&self, lane: u32) -> BlockField<N> {
+        let val = self
+            .cells
+            .iter()
+            .enumerate()
+            .fold(0, |acc, (i, cell)| acc | (((cell >> lane) & 1) << i));
+        BlockField::new(val, self.size)
+    }
+
+    /// Дорожки, в которых это поле, повёрнутое влево на `count` клеток, совпадает с полем
+    /// `other`.
+    pub fn eq_rotated_left(&self, other: &Self, count: u32) -> u64 {
+        let size = self.size as usize;
+        let count = count as usize % size;
+        (0..size).fold(!0, |acc, i| {
+            acc & !(self.cells[(i + size - count) % size] ^ other.cells[i])
+        })
+    }
+}
+
+impl<const N: usize> Cells<N> for BatchField<N> {
+    fn apply_phase(&mut self, rule: &BlockRule<N>, phase: u32) {
+        BatchRule::from_rule(rule).step(self, phase);
+    }
+
+    fn apply_steps(&mut self, rule: &BlockRule<N>) {
+        BatchRule::from_rule(rule).steps(self);
+    }
+}
+
+/// [`gliders::exact_period`] для всех дорожек сразу, не более чем за `max_steps` шагов.
+pub fn periods<const N: usize>(
+    x: &BatchField<N>,
+    rule: &BatchRule<N>,
+    max_steps: Option<u64>,
+) -> [Option<(u64, i32)>; 64] {
+    let mut result = [None; 64];
+    let mut found = 0u64;
+    let mut current = x.clone();
+    let blocks = (x.size / N as u32) as i32;
+    for period in 1..=max_steps.unwrap_or(u64::MAX) {
+        rule.steps(&mut current);
+        for offset in (0..=blocks / 2).flat_map(|offset| [-offset, offset]) {
+            // поворот на `offset` блоков это поворот влево на `-offset` блоков
+            let shift = (-offset).rem_euclid(blocks) as u32 * N as u32;
+            let mut new = current.eq_rotated_left(x, shift) & !found;
+            found |= new;
+            while new != 0 {
+                let lane = new.trailing_zeros();
+                result[lane as usize] = Some((period, offset));
+                new &= new - 1;
+            }
+        }
+        if found == !0 {
+            break;
+        }
+    }
+    result
+}
+
+/// [`gliders::get_gliders`] для каждого правила из `rules`. Правила идут по 64 в дорожках:
+/// период каждого кандидата сначала ищется во всех дорожках сразу, и полная проверка
+/// [`gliders::is_this_glider`] достаётся только правилам, в которых период нашёлся.
+pub fn get_gliders<const N: usize>(
+    rules: &[BlockRule<N>],
+    max_count: u64,
+    max_period: Option<u64>,
+) -> Vec<BTreeSet<(u64, u64, i32)>> {
+    let mut result = Vec::with_capacity(rules.len());
+    for chunk in rules.chunks(64) {
+        let batch_rule = BatchRule::new(chunk);
+        let mut catalogues = chunk
+            .iter()
+            .map(|_| gliders::Catalogue::new())
+            .collect::<Vec<_>>();
+        for x in 1..max_count {
+            let x = BlockField::<N>::new(x, BlockField::<N>::MAX_SIZE);
+            // то же кольцо, на котором период ищет `is_this_glider`
+            let size = x.occupied() + 9;
+            if size > BlockField::<N>::MAX_POSITIONS {
+                continue;
+            }
+            let ring = BatchField::from_fields(&vec![x.resize(size); chunk.len()]);
+            let found = periods(&ring, &batch_rule, max_period);
+            for ((rule, catalogue), found) in chunk.iter().zip(&mut catalogues).zip(found) {
+                if found.is_none() {
+                    continue;
+                }
+                if let Some((min, period, offset)) = gliders::is_this_glider(x, rule, max_period) {
+                    catalogue.insert(rule, min, period, offset);
+                }
+            }
+        }
+        result.extend(catalogues.into_iter().map(|catalogue| catalogue.gliders));
+    }
+    result
+}
+
+/// Время поиска глайдеров для `rules` через [`get_gliders`] и по одному правилу через
+/// [`gliders::get_gliders`]. На 128 правилах окна 3 и 3000 кандидатах первое примерно вдвое
+/// быстрее.
+pub fn benchmark_gliders<const N: usize>(
+    rules: &[BlockRule<N>],
+    max_count: u64,
+    max_period: Option<u64>,
+) -> [Duration; 2] {
+    let start = Instant::now();
+    black_box(get_gliders(rules, max_count, max_period));
+    let batch = start.elapsed();
+    let start = Instant::now();
+    for rule in rules {
+        black_box(gliders::get_gliders(rule, max_count, max_period, false));
+    }
+    [batch, start.elapsed()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window3::{Field, Rule};
+    use crate::Random;
+
+    #[test]
+    fn same_as_block_field() {
+        let mut random = Random::new(25025069121927896);
+        let rules = (0..64)
+            .map(|_| Rule::num_to_rule(random.get() % 40320))
+            .collect::<Vec<_>>();
+        let mut fields = (0..64)
+            .map(|_| Field::new(random.get() & low_mask(45), 45))
+            .collect::<Vec<_>>();
+        let batch_rule = BatchRule::new(&rules);
+        let mut batch = BatchField::from_fields(&fields);
+        for _ in 0..20 {
+            batch_rule.steps(&mut batch);
+            for (x, rule) in fields.iter_mut().zip(&rules) {
+                rule.steps(x);
+            }
+            for (lane, x) in fields.iter().enumerate() {
+                assert_eq!(batch.lane(lane as u32), *x);
+            }
+        }
+
+        let rule = BlockRule::<4>::num_to_rule(123_456_789);
+        let mut x = BlockField::<4>::new(random.get() & low_mask(64), 64);
+        let mut batch = BatchField::from_fields(&[x]);
+        rule.steps_count(&mut x, 10);
+        rule.steps_count(&mut batch, 10);
+        assert_eq!(batch.lane(0), x);
+    }
+
+    #[test]
+    fn same_gliders() {
+        let rules = (0..70)
+            .map(|ni| Rule::num_to_rule(ni * 571))
+            .collect::<Vec<_>>();
+        let found = get_gliders(&rules, 300, Some(200));
+        for (rule, found) in rules.iter().zip(found) {
+            assert_eq!(found, gliders::get_gliders(rule, 300, Some(200), false));
+        }
+    }
+
+    #[test]
+    fn same_periods() {
+        for ni in [173, 633, 876] {
+            let rule = Rule::num_to_rule(ni);
+            let fields = (1..65).map(|x| Field::new(x, 30)).collect::<Vec<_>>();
+            let found = periods(
+                &BatchField::from_fields(&fields),
+                &BatchRule::from_rule(&rule),
+                Some(60),
+            );
+            for (x, found) in fields.iter().zip(found) {
+                assert_eq!(found, gliders::exact_period(*x, &rule, Some(60)));
+            }
+        }
+    }
+}
@@ -0,0 +1,233 @@
+//! Нециклические границы для [`BlockField`].
+//!
+//! На фазе со смещением `phase != 0` у каждого края остаётся неполный блок: `phase` клеток
+//! снизу и `N - phase` клеток сверху. Недостающие клетки блока (призраки) берутся из границы,
+//! правило применяется ко всему блоку, а новые значения призраков выбрасываются.
+
+use super::*;
+use std::collections::HashSet;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Boundary {
+    /// Поле замкнуто в кольцо, как у обычного [`BlockField`]. Обратимо для любого правила.
+    Cyclic,
+    /// За границей бесконечная стена из клеток с этим значением. Обратимость сохраняется
+    /// только для немногих правил, проверяется [`Boundary::is_reversible`].
+    Fixed(bool),
+    /// За границей зеркальное отражение поля: призрак на расстоянии `d` от края равен клетке
+    /// на расстоянии `d` внутри поля. Обратимость сохраняется ещё для меньшего числа правил,
+    /// чем у `Fixed`.
+    Mirror,
+    /// Неполные блоки не меняются. Каждая фаза остаётся перестановкой, поэтому обратимость
+    /// сохраняется для любого правила.
+    Frozen,
+    /// Неполные блоки обнуляются: всё, что дошло до края, исчезает. Необратимо ни для какого
+    /// правила.
+    Absorbing,
+}
+
+/// Поле с границами, шагается через [`Cells`] как обычное.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct BoundedField<const N: usize> {
+    pub field: BlockField<N>,
+    pub boundary: Boundary,
+}
+
+impl<const N: usize> BoundedField<N> {
+    pub fn new(field: BlockField<N>, boundary: Boundary) -> Self {
+        Self { field, boundary }
+    }
+
+    pub fn to_bools(&self, dupl: u64) -> Vec<bool> {
+        self.field.to_bools(dupl)
+    }
+
+    // значение призрака снизу на расстоянии `d` от края
+    fn ghost_low(&self, d: u32) -> bool {
+        match self.boundary {
+            Boundary::Fixed(value) => value,
+            Boundary::Mirror => self.field.index(d),
+            _ => false,
+        }
+    }
+
+    // значение призрака сверху на расстоянии `d` от края
+    fn ghost_high(&self, d: u32) -> bool {
+        match self.boundary {
+            Boundary::Fixed(value) => value,
+            Boundary::Mirror => self.field.index(self.field.size() - 1 - d),
+            _ => false,
+        }
+    }
+}
+
+impl<const N: usize> Cells<N> for BoundedField<N> {
+    fn apply_phase(&mut self, rule: &BlockRule<N>, phase: u32) {
+        let n = N as u32;
+        let phase = phase % n;
+        if self.boundary == Boundary::Cyclic || phase == 0 {
+            rule.step(&mut self.field, phase);
+            return;
+        }
+
+        let size = self.field.size();
+        let val = self.field.val();
+        let middle_size = size - n;
+        let middle = (rule.replace_word(val >> phase) & low_mask(middle_size)) << phase;
+
+        // нижний блок: призраки в младших `n - phase` битах, клетки 0..phase в старших
+        let low_cells = val & low_mask(phase);
+        let high_start = size - n + phase;
+        let high_cells = val >> high_start;
+        let (low, high) = match self.boundary {
+            Boundary::Frozen => (low_cells, high_cells),
+            Boundary::Absorbing => (0, 0),
+            _ => {
+                let mut low_block = low_cells << (n - phase);
+                for d in 0..n - phase {
+                    low_block |= (self.ghost_low(d) as u64) << (n - phase - 1 - d);
+                }
+                let mut high_block = high_cells;
+                for d in 0..phase {
+                    high_block |= (self.ghost_high(d) as u64) << (n - phase + d);
+                }
+                let low = (rule.replace_word(low_block) & low_mask(n)) >> (n - phase);
+                let high = rule.replace_word(high_block) & low_mask(n - phase);
+                (low, high)
+            }
+        };
+
+        self.field = BlockField::new(middle | low | (high << high_start), size);
+    }
+}
+
+impl Boundary {
+    /// Проверяет, что с этой границей каждая фаза правила остаётся перестановкой. Граница
+    /// действует только на `N - 1` клеток у края, поэтому достаточно перебрать все поля из
+    /// трёх блоков.
+    pub fn is_reversible<const N: usize>(&self, rule: &BlockRule<N>) -> bool {
+        let size = 3 * N as u32;
+        (0..N as u32).all(|phase| {
+            let mut images = HashSet::new();
+            (0..1u64 << size).all(|val| {
+                let mut x = BoundedField::new(BlockField::new(val, size), *self);
+                rule.step(&mut x, phase);
+                images.insert(x.field.val())
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window3::{Field, Rule};
+    use crate::Random;
+
+    #[test]
+    fn same_as_naive() {
+        let mut random = Random::new(25025069121927896);
+        for ni in [173, 633, 876, 20000] {
+            let rule = Rule::num_to_rule(ni);
+            for boundary in [
+                Boundary::Fixed(false),
+                Boundary::Fixed(true),
+                Boundary::Mirror,
+                Boundary::Frozen,
+                Boundary::Absorbing,
+            ] {
+                let mut x =
+                    BoundedField::new(Field::new(random.get() & low_mask(30), 30), boundary);
+                let mut cells = (0..30).map(|i| x.field.index(i)).collect::<Vec<_>>();
+                let permutation = rule.permutation();
+                // призраки выписаны явно, а не через `ghost_low` и `ghost_high`
+                let ghost = |old: &[bool], pos: i64| match boundary {
+                    Boundary::Fixed(value) => value,
+                    Boundary::Mirror if pos < 0 => old[(-1 - pos) as usize],
+                    Boundary::Mirror => old[(2 * old.len() as i64 - 1 - pos) as usize],
+                    _ => false,
+                };
+                let replace = |block: usize, partial: bool| match boundary {
+                    Boundary::Frozen if partial => block,
+                    Boundary::Absorbing if partial => 0,
+                    _ => permutation[block],
+                };
+                for _ in 0..20 {
+                    rule.steps(&mut x);
+                    for phase in 0..3 {
+                        naive_phase::<3>(&mut cells, phase, ghost, replace);
+                    }
+                    assert_eq!((0..30).map(|i| x.field.index(i)).collect::<Vec<_>>(), cells);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cyclic_is_ring() {
+        let rule = Rule::num_to_rule(633);
+        let mut x = Field::new(0b101_000_110_011, 30);
+        let mut y = BoundedField::new(x, Boundary::Cyclic);
+        rule.steps_count(&mut x, 50);
+        rule.steps_count(&mut y, 50);
+        assert_eq!(x, y.field);
+    }
+
+    #[test]
+    fn reversibility() {
+        for ni in (0..40320).step_by(97) {
+            let rule = Rule::num_to_rule(ni);
+            assert!(Boundary::Cyclic.is_reversible(&rule));
+            assert!(Boundary::Frozen.is_reversible(&rule));
+            assert!(!Boundary::Absorbing.is_reversible(&rule));
+        }
+        let rule = Rule::num_to_rule(633);
+        assert!(!Boundary::Fixed(false).is_reversible(&rule));
+        assert!(!Boundary::Fixed(true).is_reversible(&rule));
+        assert!(!Boundary::Mirror.is_reversible(&rule));
+        assert!(Boundary::Fixed(false).is_reversible(&Rule::RULE0));
+        assert!(Boundary::Mirror.is_reversible(&Rule::RULE0));
+    }
+
+    // середина занятых клеток
+    fn center(x: &BoundedField<3>) -> f64 {
+        let cells = (0..60).filter(|i| x.field.index(*i)).collect::<Vec<_>>();
+        cells.iter().sum::<u32>() as f64 / cells.len() as f64
+    }
+
+    #[test]
+    fn gliders_at_walls() {
+        let rule = Rule::num_to_rule(633);
+        // 0b101 летит к старшим клеткам, 0b1010 к младшим
+        for (glider, wall) in [(0b101 << 30, 59), (0b1010 << 30, 0)] {
+            // у замороженной стены глайдер отражается и летит обратно
+            let mut x = BoundedField::new(Field::new(glider, 60), Boundary::Frozen);
+            let mut centers = Vec::new();
+            for _ in 0..6 {
+                centers.push(center(&x));
+                rule.steps_count(&mut x, 10);
+            }
+            let to_wall = |a: f64, b: f64| (b - a) * (wall as f64 - 30.0) > 0.0;
+            assert!(to_wall(centers[0], centers[1]));
+            assert!(!to_wall(centers[4], centers[5]));
+
+            // у остальных стен глайдер застревает и становится осциллятором у самой стены
+            for boundary in [
+                Boundary::Fixed(false),
+                Boundary::Fixed(true),
+                Boundary::Mirror,
+                Boundary::Absorbing,
+            ] {
+                let mut x = BoundedField::new(Field::new(glider, 60), boundary);
+                rule.steps_count(&mut x, 100);
+                let stuck = x;
+                rule.steps_count(&mut x, 20);
+                assert_eq!(x, stuck);
+                assert!(x.field.val() != 0);
+                assert!((0..60)
+                    .filter(|i| x.field.index(*i))
+                    .all(|i| i.abs_diff(wall) < 6));
+            }
+        }
+    }
+}
@@ -0,0 +1,163 @@
+//! Классы правил с точностью до симметрий.
+//!
+//! Точные симметрии в классе обычных правил это [`Symmetry::MirrorTime`] и
+//! [`Symmetry::Color`] (см. [`super::symmetry`]), вместе с композицией они образуют группу из
+//! четырёх элементов. Зеркало и обращение времени сохраняют всё, что считается на пустом поле:
+//! периоды, смещения и количество глайдеров. Инверсия цвета переводит пустое поле в
+//! заполненное, поэтому её стоит добавлять, только если результат не зависит от фона.
+
+use super::*;
+use std::collections::{BTreeSet, HashMap};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Symmetry {
+    /// [`BlockRule::mirror_time`]
+    MirrorTime,
+    /// [`BlockRule::invert_color`]
+    Color,
+    /// Обе симметрии сразу.
+    ColorMirrorTime,
+}
+
+impl Symmetry {
+    pub const ALL: [Symmetry; 3] = [
+        Symmetry::MirrorTime,
+        Symmetry::Color,
+        Symmetry::ColorMirrorTime,
+    ];
+
+    pub fn apply<const N: usize>(&self, rule: &BlockRule<N>) -> BlockRule<N> {
+        match self {
+            Symmetry::MirrorTime => rule.mirror_time(),
+            Symmetry::Color => rule.invert_color(),
+            Symmetry::ColorMirrorTime => rule.invert_color().mirror_time(),
+        }
+    }
+
+    // группа, порождённая `generators`, без тождественного элемента
+    fn closure(generators: &[Symmetry]) -> Vec<Symmetry> {
+        let has = |s| generators.contains(&s);
+        Self::ALL
+            .into_iter()
+            .filter(|s| match s {
+                Symmetry::ColorMirrorTime => {
+                    has(*s) || (has(Symmetry::Color) && has(Symmetry::MirrorTime))
+                }
+                Symmetry::MirrorTime => {
+                    has(*s) || (has(Symmetry::Color) && has(Symmetry::ColorMirrorTime))
+                }
+                Symmetry::Color => {
+                    has(*s) || (has(Symmetry::MirrorTime) && has(Symmetry::ColorMirrorTime))
+                }
+            })
+            .collect()
+    }
+}
+
+/// Класс эквивалентных правил.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct RuleClass {
+    /// Минимальный номер в классе.
+    pub representative: u64,
+    /// Номера всех правил класса по возрастанию, включая представителя.
+    pub members: Vec<u64>,
+    /// Симметрии, которые переводят правила класса в себя.
+    pub stabilizer: Vec<Symmetry>,
+}
+
+/// Разбивает все `(2^N)!` правил на классы относительно группы, порождённой `generators`.
+/// Классы отсортированы по представителю. Перебирает все правила, поэтому только для `N <= 3`.
+pub fn rule_classes<const N: usize>(generators: &[Symmetry]) -> Vec<RuleClass> {
+    assert!(N <= 3);
+    let group = Symmetry::closure(generators);
+    let count = (1..=BlockRule::<N>::STATES as u64).product::<u64>();
+    let numbers = (0..count)
+        .map(|ni| (BlockRule::<N>::num_to_rule(ni), ni))
+        .collect::<HashMap<_, _>>();
+
+    let mut used = vec![false; count as usize];
+    let mut result = Vec::new();
+    for ni in 0..count {
+        if used[ni as usize] {
+            continue;
+        }
+        let rule = BlockRule::<N>::num_to_rule(ni);
+        let mut members = BTreeSet::new();
+        members.insert(ni);
+        for s in &group {
+            members.insert(numbers[&s.apply(&rule)]);
+        }
+        for member in &members {
+            used[*member as usize] = true;
+        }
+        result.push(RuleClass {
+            representative: ni,
+            members: members.into_iter().collect(),
+            stabilizer: group
+                .iter()
+                .cloned()
+                .filter(|s| s.apply(&rule) == rule)
+                .collect(),
+        });
+    }
+    result
+}
+
+/// Таблица `номер правила -> представитель его класса`.
+pub fn canonical_rules(classes: &[RuleClass]) -> Vec<u64> {
+    let count = classes.iter().map(|class| class.members.len()).sum();
+    let mut result = vec![0; count];
+    for class in classes {
+        for member in &class.members {
+            result[*member as usize] = class.representative;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window3::{Field, Rule};
+
+    #[test]
+    fn window_3_classes() {
+        let classes = rule_classes::<3>(&[Symmetry::MirrorTime]);
+        let canonical = canonical_rules(&classes);
+        assert_eq!(canonical.len(), 40320);
+        for class in &classes {
+            assert_eq!(class.representative, class.members[0]);
+            assert!(class.members.len() <= 2);
+            assert_eq!(class.members.len() == 1, !class.stabilizer.is_empty());
+            for member in &class.members {
+                assert_eq!(canonical[*member as usize], class.representative);
+            }
+        }
+
+        let all = rule_classes::<3>(&[Symmetry::MirrorTime, Symmetry::Color]);
+        assert!(all.len() < classes.len());
+        assert!(all.iter().all(|class| class.members.len() <= 4));
+        assert_eq!(all[0].representative, 0);
+        assert_eq!(all[0].stabilizer, Symmetry::ALL.to_vec());
+    }
+
+    #[test]
+    fn mirror_time_keeps_gliders() {
+        let classes = rule_classes::<3>(&[Symmetry::MirrorTime]);
+        for class in classes
+            .iter()
+            .filter(|class| class.members.len() == 2)
+            .take(50)
+        {
+            let a = Rule::num_to_rule(class.members[0]);
+            let b = Rule::num_to_rule(class.members[1]);
+            for val in [0b1, 0b101, 0b1010, 0b110_011] {
+                let x = Field::new(val, 30);
+                assert_eq!(
+                    gliders::exact_period(x, &a, Some(60)),
+                    gliders::exact_period(x.mirror().rotate_right(1), &b, Some(60))
+                );
+            }
+        }
+    }
+}
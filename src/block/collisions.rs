@@ -0,0 +1,308 @@
+//! Каталог столкновений глайдеров для любого правила.
+//!
+//! Для каждой пары глайдеров из каталога, которые сближаются, перебираются фазы обоих и
+//! расстояние между ними. Столкновение считается на бесконечном пустом поле
+//! [`InfiniteField`], пока поле не распадётся на периодические куски, которые разлетаются и
+//! больше не встретятся. Продукты сравниваются с каталогом, по ним определяется исход.
+//!
+//! Одно и то же столкновение получается из разных начальных расстояний: чем меньше
+//! расстояние, тем позже момент. Поэтому расстояния перебираются по убыванию, и начальное
+//! состояние, которое уже встречалось в эволюции другого столкновения, пропускается.
+
+use super::infinite::{self, InfiniteField};
+use super::*;
+use std::collections::HashSet;
+
+/// Глайдер или осциллятор из каталога: минимальная форма, период и смещение в блоках, как
+/// в [`gliders::get_gliders`].
+pub type Species = (u64, u64, i32);
+
+/// Продукт столкновения.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Product {
+    /// Абсолютная координата начала формы к концу столкновения.
+    pub position: i64,
+    /// Минимальная по фазам форма, клетка 0 первая.
+    pub shape: Vec<bool>,
+    pub period: u64,
+    pub offset: i32,
+    /// Номер в каталоге, если такой глайдер там есть.
+    pub species: Option<usize>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Outcome {
+    /// Продукты это те же два глайдера или осциллятора.
+    PassThrough,
+    /// Продукты это глайдеры из каталога, но не те же два.
+    Scattering,
+    /// Среди продуктов есть глайдер, которого нет в каталоге.
+    NewGlider,
+    /// Среди продуктов есть другие осцилляторы или натюрморты.
+    Debris,
+    /// Поле не распалось на продукты за отведённое число шагов.
+    Unresolved,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Collision {
+    /// Номера глайдеров в каталоге: `left` начинает слева, `right` справа.
+    pub left: usize,
+    pub right: usize,
+    /// Сколько шагов сделал каждый глайдер из минимальной формы перед расстановкой.
+    pub left_phase: u64,
+    pub right_phase: u64,
+    /// Количество пустых блоков между глайдерами в начале.
+    pub gap: u32,
+    /// Через сколько шагов поле распалось на продукты.
+    pub steps: u64,
+    /// Продукты слева направо, пусто для [`Outcome::Unresolved`].
+    pub products: Vec<Product>,
+    pub outcome: Outcome,
+}
+
+/// Минимальное расстояние в клетках между продуктами, при котором они считаются отдельными.
+/// За шаг информация уходит на `N * (N - 1)` клеток, запас взят вдвое больше.
+pub(super) const fn separation(n: usize) -> i64 {
+    (2 * n * n) as i64
+}
+
+fn from_u64<const N: usize, R: Evolution<N>>(rule: &R, val: u64, phase: u64) -> InfiniteField<N> {
+    let mut x = InfiniteField::<N>::from_u64(val);
+    rule.steps_count(&mut x, phase);
+    x
+}
+
+// минимальная по фазам форма периодического куска
+pub(super) fn canonical<const N: usize, R: Evolution<N>>(
+    rule: &R,
+    mut x: InfiniteField<N>,
+    period: u64,
+) -> Vec<bool> {
+    let mut result = x.shape().1;
+    for _ in 1..period {
+        rule.steps(&mut x);
+        result = result.min(x.shape().1);
+    }
+    result
+}
+
+// куски поля, разделённые промежутками фона длиннее `separation`
+pub(super) fn split<const N: usize>(x: &InfiniteField<N>) -> Vec<InfiniteField<N>> {
+    let (from, to) = match x.extent() {
+        Some(extent) => extent,
+        None => return Vec::new(),
+    };
+    let mut result = Vec::new();
+    let mut start = from;
+    let mut last = from;
+    for pos in from..to {
+        if x.differs(pos) {
+            if pos - last > separation(N) {
+                result.push((start, last + 1));
+                start = pos;
+            }
+            last = pos;
+        }
+    }
+    result.push((start, last + 1));
+    result
+        .into_iter()
+        .map(|(from, to)| {
+            let mut part = InfiniteField::new(x.background());
+            for pos in from..to {
+                part.set(pos, x.get(pos));
+            }
+            part
+        })
+        .collect()
+}
+
+// минимальные по фазам формы глайдеров каталога
+pub(super) fn shapes<const N: usize, R: Evolution<N>>(
+    rule: &R,
+    catalogue: &[Species],
+) -> Vec<Vec<bool>> {
+    catalogue
+        .iter()
+        .map(|(val, period, _)| canonical(rule, from_u64(rule, *val, 0), *period))
+        .collect()
+}
+
+// скорость `a` больше скорости `b`
+fn faster(a: (u64, i32), b: (u64, i32)) -> bool {
+    a.1 as i64 * b.0 as i64 > b.1 as i64 * a.0 as i64
+}
+
+/// Раскладывает поле на продукты, если все куски периодические с периодом не больше
+/// `max_period` и никакие два соседних куска не сближаются.
+pub fn products<const N: usize, R: Evolution<N>>(
+    rule: &R,
+    x: &InfiniteField<N>,
+    catalogue: &[Vec<bool>],
+    max_period: u64,
+) -> Option<Vec<Product>> {
+    let mut result: Vec<Product> = Vec::new();
+    for part in split(x) {
+        let (period, offset) = infinite::period(part.clone(), rule, max_period)?;
+        if let Some(prev) = result.last() {
+            if faster((prev.period, prev.offset), (period, offset)) {
+                return None;
+            }
+        }
+        let shape = canonical(rule, part.clone(), period);
+        result.push(Product {
+            position: part.shape().0,
+            species: catalogue.iter().position(|known| *known == shape),
+            shape,
+            period,
+            offset,
+        });
+    }
+    Some(result)
+}
+
+fn outcome(left: usize, right: usize, products: &[Product]) -> Outcome {
+    let mut species = products
+        .iter()
+        .map(|product| product.species)
+        .collect::<Vec<_>>();
+    species.sort();
+    let mut before = vec![Some(left), Some(right)];
+    before.sort();
+    if products
+        .iter()
+        .any(|product| product.offset != 0 && product.species.is_none())
+    {
+        Outcome::NewGlider
+    } else if species == before {
+        Outcome::PassThrough
+    } else if products.iter().any(|product| product.offset == 0) {
+        Outcome::Debris
+    } else {
+        Outcome::Scattering
+    }
+}
+
+/// Все столкновения пар из каталога `catalogue`: глайдер слева должен быть быстрее глайдера
+/// справа, фазы перебираются полностью, расстояние от 1 до `max_gap` пустых блоков. Каждое
+/// столкновение считается не дольше `max_steps` шагов.
+pub fn collide_all<const N: usize, R: Evolution<N>>(
+    rule: &R,
+    catalogue: &[Species],
+    max_gap: u32,
+    max_steps: u64,
+) -> Vec<Collision> {
+    let mut empty = BlockField::<N>::new(0, N as u32);
+    rule.steps(&mut empty);
+    assert_eq!(empty.val(), 0, "rule must keep the empty field empty");
+
+    let max_period = catalogue
+        .iter()
+        .map(|s| s.1)
+        .max()
+        .unwrap_or(1)
+        .max(max_steps);
+    let shapes = shapes(rule, catalogue);
+
+    let mut result = Vec::new();
+    for (left, a) in catalogue.iter().enumerate() {
+        for (right, b) in catalogue.iter().enumerate() {
+            if !faster((a.1, a.2), (b.1, b.2)) {
+                continue;
+            }
+            let mut seen = HashSet::new();
+            for gap in (1..=max_gap).rev() {
+                for left_phase in 0..a.1 {
+                    for right_phase in 0..b.1 {
+                        let mut x = from_u64(rule, a.0, left_phase);
+                        let y = from_u64(rule, b.0, right_phase);
+                        let (_, a_to) = x.extent().unwrap();
+                        let (b_from, b_to) = y.extent().unwrap();
+                        let n = N as i64;
+                        let shift = (a_to - b_from).div_euclid(n) * n + n + gap as i64 * n;
+                        for pos in b_from..b_to {
+                            if y.get(pos) {
+                                x.set(pos + shift, true);
+                            }
+                        }
+                        if !seen.insert(x.shape().1) {
+                            continue;
+                        }
+
+                        let mut collision = Collision {
+                            left,
+                            right,
+                            left_phase,
+                            right_phase,
+                            gap,
+                            steps: max_steps,
+                            products: Vec::new(),
+                            outcome: Outcome::Unresolved,
+                        };
+                        for step in 1..=max_steps {
+                            rule.steps(&mut x);
+                            seen.insert(x.shape().1);
+                            if !step.is_multiple_of(N as u64) {
+                                continue;
+                            }
+                            if let Some(products) = products(rule, &x, &shapes, max_period) {
+                                collision.steps = step;
+                                collision.outcome = outcome(left, right, &products);
+                                collision.products = products;
+                                break;
+                            }
+                        }
+                        result.push(collision);
+                    }
+                }
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window3::Rule;
+
+    #[test]
+    fn rule_633() {
+        let rule = Rule::num_to_rule(633);
+        let catalogue = gliders::get_gliders(&rule, 64, Some(200), false)
+            .into_iter()
+            .collect::<Vec<_>>();
+        let index = |species: Species| catalogue.iter().position(|s| *s == species).unwrap();
+        let collisions = collide_all(&rule, &catalogue, 3, 200);
+        assert!(collisions.iter().all(|c| c.outcome != Outcome::Unresolved));
+        for c in &collisions {
+            let a = catalogue[c.left];
+            let b = catalogue[c.right];
+            assert!(faster((a.1, a.2), (b.1, b.2)));
+            assert_eq!(c.outcome, outcome(c.left, c.right, &c.products));
+            for pair in c.products.windows(2) {
+                assert!(!faster(
+                    (pair[0].period, pair[0].offset),
+                    (pair[1].period, pair[1].offset)
+                ));
+            }
+        }
+
+        // глайдеры навстречу друг другу проходят насквозь
+        let (left, right) = (index((0b101, 4, 1)), index((0b1010, 2, -1)));
+        assert!(collisions
+            .iter()
+            .filter(|c| c.left == left && c.right == right)
+            .all(|c| c.outcome == Outcome::PassThrough));
+        // глайдер переводит осциллятор в другой осциллятор
+        let (left, right) = (index((0b1, 2, 0)), index((0b1010, 2, -1)));
+        let c = collisions
+            .iter()
+            .find(|c| c.left == left && c.right == right)
+            .unwrap();
+        assert_eq!(c.outcome, Outcome::Debris);
+        assert_eq!(c.products[0].species, Some(right));
+        assert_eq!(c.products[1].species, Some(index((0b10, 2, 0))));
+    }
+}
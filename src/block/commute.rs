@@ -0,0 +1,97 @@
+//! Точная проверка коммутации двух эволюций.
+//!
+//! За полный шаг клетка зависит только от ограниченного окна клеток, поэтому `A B x` и
+//! `B A x` это клеточные автоматы с конечным окном. Они совпадают на бесконечной прямой
+//! тогда и только тогда, когда совпадают на всех кольцах размера `L`, где `L` не меньше
+//! окна и кратен `N`: любое окно помещается в такое кольцо с тем же выравниванием блоков,
+//! а кольцо само по себе это периодическое поле на прямой. Кольца меньшего размера тоже
+//! периодические поля, поэтому отличие на них это настоящий контрпример.
+
+use super::*;
+
+/// Ширина окна, от которого зависит клетка после фаз со смещениями `offsets` по порядку.
+pub fn dependency_width<const N: usize>(offsets: &[u32]) -> u32 {
+    let n = N as i64;
+    (0..n)
+        .map(|c| {
+            let (mut lo, mut hi) = (c, c);
+            for offset in offsets.iter().rev().map(|x| *x as i64) {
+                lo -= (lo - offset).rem_euclid(n);
+                hi += n - 1 - (hi - offset).rem_euclid(n);
+            }
+            (hi - lo + 1) as u32
+        })
+        .max()
+        .unwrap()
+}
+
+fn offsets<const N: usize, R: Evolution<N>>(rule: &R) -> Vec<u32> {
+    (0..rule.phases()).map(|phase| rule.offset(phase)).collect()
+}
+
+/// Размер колец, которых достаточно для доказательства коммутации `a` и `b`.
+pub fn proof_size<const N: usize, A: Evolution<N>, B: Evolution<N>>(a: &A, b: &B) -> u32 {
+    let ab = [offsets(a), offsets(b)].concat();
+    let ba = [offsets(b), offsets(a)].concat();
+    let width = dependency_width::<N>(&ab).max(dependency_width::<N>(&ba));
+    width.div_ceil(N as u32) * N as u32
+}
+
+/// Проверяет `A B x == B A x` на всех кольцах размера от `N` до [`proof_size`] по возрастанию.
+/// `Ok(size)` означает, что правила коммутируют на бесконечной прямой, а `size` это размер
+/// колец, на которых это проверено. `Err(x)` это минимальное поле, на котором они не
+/// коммутируют. Перебор растёт как `2^size`, поэтому практичен для окна не больше 3.
+pub fn check_commute<const N: usize, A: Evolution<N>, B: Evolution<N>>(
+    a: &A,
+    b: &B,
+) -> Result<u32, BlockField<N>> {
+    let max_size = proof_size(a, b);
+    assert!(max_size <= BlockField::<N>::MAX_SIZE);
+    for size in (N as u32..=max_size).step_by(N) {
+        for val in 0..=low_mask(size) {
+            let x = BlockField::<N>::new(val, size);
+            let mut x1 = x;
+            let mut x2 = x;
+            a.steps(&mut x1);
+            b.steps(&mut x1);
+            b.steps(&mut x2);
+            a.steps(&mut x2);
+            if x1 != x2 {
+                return Err(x);
+            }
+        }
+    }
+    Ok(max_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window3::Rule;
+
+    #[test]
+    fn width() {
+        assert_eq!(dependency_width::<3>(&[0]), 3);
+        assert_eq!(dependency_width::<3>(&[0, 1, 2]), 9);
+        assert_eq!(proof_size(&Rule::RULE0, &Rule::RULE0), 18);
+    }
+
+    #[test]
+    fn commute() {
+        let a = Rule::num_to_rule(633);
+        assert_eq!(check_commute(&a, &a), Ok(18));
+        let back = schedule::Schedule::from(a).inverse();
+        assert!(check_commute(&a, &back).is_ok());
+        assert_eq!(check_commute(&a, &Rule::RULE0), Ok(18));
+
+        let b = Rule::num_to_rule(20000);
+        let x = check_commute(&a, &b).unwrap_err();
+        let (mut x1, mut x2) = (x, x);
+        a.steps(&mut x1);
+        b.steps(&mut x1);
+        b.steps(&mut x2);
+        a.steps(&mut x2);
+        assert_ne!(x1, x2);
+        assert_eq!(x.size(), 3);
+    }
+}
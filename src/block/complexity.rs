@@ -0,0 +1,318 @@
+//! Меры сложности пространственно-временных диаграмм и автоматическая классификация правил.
+//!
+//! Обратимое правило сохраняет равномерную меру, поэтому из случайного поля с плотностью 1/2
+//! все правила выглядят одинаково случайными. Начальные поля берутся разреженными, с
+//! плотностью [`Settings::density`], и меряется, насколько правило их перемешивает:
+//!
+//! - пространственная энтропия: прирост энтропии слов из соседних клеток при удлинении слова
+//!   с `word - 1` до [`Settings::word`] клеток, то есть оценка энтропии на клетку;
+//! - временная энтропия: то же для слов из последовательных состояний одной клетки;
+//! - сжатие: размер диаграммы после LZ78 относительно исходного, по строкам или по столбцам;
+//! - автокорреляция: корреляция состояния клетки с её состоянием через несколько шагов;
+//! - периодичность: доля полей, которые за несколько шагов переходят в свой поворот.
+//!
+//! По этим числам правилу приписывается тип поведения [`Behaviour`] в духе классификации
+//! Вольфрама.
+
+use super::wide::WideField;
+use super::*;
+use crate::Random;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Параметры измерения.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Settings {
+    /// Размер кольца в клетках, кратный `N`.
+    pub size: u32,
+    /// Шаги, которые пропускаются перед измерением.
+    pub transient: u64,
+    /// Количество полных шагов в диаграмме. При 0 все меры диаграммы нулевые.
+    pub steps: u64,
+    /// Количество случайных начальных полей.
+    pub seeds: u64,
+    /// Доля единиц в начальном поле.
+    pub density: f64,
+    /// Длина слов для энтропий.
+    pub word: u32,
+    /// Наибольший сдвиг по времени для автокорреляции.
+    pub max_lag: u64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            size: 600,
+            transient: 100,
+            steps: 200,
+            seeds: 4,
+            density: 0.2,
+            word: 8,
+            max_lag: 20,
+        }
+    }
+}
+
+/// Меры сложности, усреднённые по начальным полям. Энтропии в битах на клетку.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Complexity {
+    pub spatial_entropy: f64,
+    pub temporal_entropy: f64,
+    /// Меньший из размеров после LZ78 по строкам и по столбцам в битах, делённый на
+    /// количество клеток диаграммы. У случайной диаграммы немного больше 1.
+    pub compression: f64,
+    /// Средняя по сдвигам от 1 до [`Settings::max_lag`] нормированная автокорреляция.
+    pub autocorrelation: f64,
+    /// Доля начальных полей, которые к концу диаграммы повторяются с точностью до поворота на
+    /// целое число блоков с периодом не больше [`Settings::max_lag`].
+    pub periodic: f64,
+}
+
+/// Тип поведения правила. Не путать с [`super::classes::RuleClass`], классом симметрии.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Behaviour {
+    /// Клетки почти не меняются.
+    Trivial,
+    /// Всё поле периодически повторяется или сдвигается.
+    Periodic,
+    /// Поле перемешивается до случайного.
+    Chaotic,
+    /// Промежуточное поведение: частицы на фоне.
+    Complex,
+}
+
+impl fmt::Display for Behaviour {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Behaviour::Trivial => "trivial",
+            Behaviour::Periodic => "periodic",
+            Behaviour::Chaotic => "chaotic",
+            Behaviour::Complex => "complex",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Complexity {
+    /// Тип поведения правила по мерам сложности:
+    ///
+    /// - временная энтропия около нуля: клетки не меняются или всё поле повторяется;
+    /// - сжатие не меньше 1.15: диаграмма почти не сжимается, как случайная;
+    /// - сжатие от 0.9 до 1.15: структура на почти случайном фоне;
+    /// - сжатие меньше 0.9: диаграмма заметно сжимается, и по мерам нельзя отличить частицы от
+    ///   локальных осцилляторов. Тогда решает количество глайдеров с ненулевым смещением,
+    ///   например из [`gliders::get_gliders`].
+    pub fn behaviour(&self, gliders: usize) -> Behaviour {
+        if self.temporal_entropy < 0.05 && self.autocorrelation > 0.9 {
+            Behaviour::Trivial
+        } else if self.temporal_entropy < 0.05 || self.periodic >= 0.5 {
+            Behaviour::Periodic
+        } else if self.compression >= 1.15 {
+            Behaviour::Chaotic
+        } else if self.compression >= 0.9 || gliders > 0 {
+            Behaviour::Complex
+        } else {
+            Behaviour::Periodic
+        }
+    }
+}
+
+fn entropy(counts: &HashMap<u64, u64>) -> f64 {
+    let total = counts.values().sum::<u64>() as f64;
+    counts
+        .values()
+        .map(|count| {
+            let p = *count as f64 / total;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+// энтропия слов длины `word` минус энтропия их префиксов длины `word - 1`
+fn entropy_rate(counts: &HashMap<u64, u64>, word: u32) -> f64 {
+    let mut prefixes = HashMap::new();
+    for (val, count) in counts {
+        *prefixes.entry(val & low_mask(word - 1)).or_insert(0) += count;
+    }
+    entropy(counts) - entropy(&prefixes)
+}
+
+// размер в битах после LZ78: каждая фраза это номер предыдущей фразы и один бит
+fn lz78_size(bits: impl Iterator<Item = bool>) -> f64 {
+    let mut phrases = HashMap::new();
+    let mut current = 0usize;
+    let mut count = 0usize;
+    for bit in bits {
+        let next = phrases.len() + 1;
+        match phrases.get(&(current, bit)) {
+            Some(phrase) => current = *phrase,
+            None => {
+                phrases.insert((current, bit), next);
+                current = 0;
+                count += 1;
+            }
+        }
+    }
+    if current != 0 {
+        count += 1;
+    }
+    count as f64 * ((count as f64).log2().max(0.0) + 1.0)
+}
+
+// меры по одной диаграмме, строки это состояния после каждого полного шага
+fn measure(diagram: &[Vec<bool>], settings: &Settings) -> Complexity {
+    if diagram.is_empty() {
+        return Complexity::default();
+    }
+    let rows = diagram.len();
+    let size = diagram[0].len();
+    let word = settings.word as usize;
+
+    assert!(word >= 2);
+    let mut spatial = HashMap::new();
+    for row in diagram {
+        for i in 0..size {
+            let val = (0..word).fold(0, |acc, k| acc | (row[(i + k) % size] as u64) << k);
+            *spatial.entry(val).or_insert(0) += 1;
+        }
+    }
+    let mut temporal = HashMap::new();
+    for window in diagram.windows(word) {
+        let column = |i| (0..word).fold(0, |acc, k| acc | (window[k][i] as u64) << k);
+        for val in (0..size).map(column) {
+            *temporal.entry(val).or_insert(0) += 1;
+        }
+    }
+
+    let cells = (rows * size) as f64;
+    let ones = diagram.iter().flatten().filter(|x| **x).count() as f64;
+    let density = ones / cells;
+    let variance = density * (1.0 - density);
+    let max_lag = (settings.max_lag as usize).min(rows - 1);
+    let autocorrelation = if variance == 0.0 || max_lag == 0 {
+        1.0
+    } else {
+        (1..=max_lag)
+            .map(|lag| {
+                let pairs = ((rows - lag) * size) as f64;
+                let both = (0..rows - lag)
+                    .map(|t| {
+                        (0..size)
+                            .filter(|i| diagram[t][*i] && diagram[t + lag][*i])
+                            .count()
+                    })
+                    .sum::<usize>() as f64;
+                (both / pairs - density * density) / variance
+            })
+            .sum::<f64>()
+            / max_lag as f64
+    };
+
+    let by_rows = lz78_size(diagram.iter().flatten().cloned());
+    let by_columns = lz78_size((0..size).flat_map(|i| diagram.iter().map(move |row| row[i])));
+
+    Complexity {
+        spatial_entropy: entropy_rate(&spatial, settings.word),
+        temporal_entropy: entropy_rate(&temporal, settings.word),
+        compression: by_rows.min(by_columns) / cells,
+        autocorrelation,
+        periodic: 0.0,
+    }
+}
+
+// поле `x` переходит в свой поворот на целое число блоков не больше чем за `max_lag` шагов
+fn is_periodic<const N: usize, R: Evolution<N>>(rule: &R, x: &WideField<N>, max_lag: u64) -> bool {
+    let blocks = (x.size() / N as u32) as i32;
+    let mut y = x.clone();
+    (0..max_lag).any(|_| {
+        rule.steps(&mut y);
+        (0..blocks).any(|shift| y.clone().rotate(shift * N as i32) == *x)
+    })
+}
+
+/// Меры сложности правила, усреднённые по [`Settings::seeds`] случайным начальным полям.
+pub fn complexity<const N: usize, R: Evolution<N>>(
+    rule: &R,
+    settings: &Settings,
+    random: &mut Random,
+) -> Complexity {
+    let mut result = Complexity::default();
+    for _ in 0..settings.seeds {
+        let mut x = WideField::<N>::random(random, settings.size, settings.density);
+        rule.steps_count(&mut x, settings.transient);
+        let mut diagram = Vec::new();
+        for _ in 0..settings.steps {
+            diagram.push((0..settings.size).map(|i| x.index(i)).collect::<Vec<_>>());
+            rule.steps(&mut x);
+        }
+        let c = measure(&diagram, settings);
+        let k = 1.0 / settings.seeds as f64;
+        result.spatial_entropy += c.spatial_entropy * k;
+        result.temporal_entropy += c.temporal_entropy * k;
+        result.compression += c.compression * k;
+        result.autocorrelation += c.autocorrelation * k;
+        if is_periodic(rule, &x, settings.max_lag) {
+            result.periodic += k;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window3::Rule;
+
+    #[test]
+    fn lz78() {
+        let mut random = Random::new(25025069121927896);
+        let noise = lz78_size((0..10000).map(|_| random.get() & 1 != 0));
+        let constant = lz78_size((0..10000).map(|_| false));
+        assert!(noise > 10000.0 && constant < noise / 5.0);
+        // фразы 1, 0, 11
+        assert_eq!(
+            lz78_size([true, false, true, true].into_iter()),
+            3.0 * (3f64.log2() + 1.0)
+        );
+    }
+
+    #[test]
+    fn behaviours() {
+        let mut random = Random::new(25025069121927896);
+        let settings = Settings::default();
+
+        let identity = complexity(&Rule::RULE0, &settings, &mut random);
+        assert!(identity.temporal_entropy.abs() < 1e-9);
+        assert_eq!(identity.behaviour(0), Behaviour::Trivial);
+
+        // каждая клетка инвертируется на каждом шаге
+        let inversion = Rule::from_permutation(&(0..8).rev().collect::<Vec<_>>());
+        let c = complexity(&inversion, &settings, &mut random);
+        assert_eq!(c.periodic, 1.0);
+        assert_eq!(c.behaviour(0), Behaviour::Periodic);
+
+        // частицы на фоне, по мерам неотличимые от осцилляторов без глайдеров
+        let c = complexity(&Rule::num_to_rule(633), &settings, &mut random);
+        assert_eq!(c.behaviour(3), Behaviour::Complex);
+        assert_eq!(c.behaviour(0), Behaviour::Periodic);
+        // здесь глайдеры уже ничего не решают
+        let c = complexity(&Rule::num_to_rule(876), &settings, &mut random);
+        assert_eq!(c.behaviour(0), Behaviour::Complex);
+        let c = complexity(&Rule::num_to_rule(20000), &settings, &mut random);
+        assert_eq!(c.behaviour(0), Behaviour::Chaotic);
+        assert_eq!(c.behaviour(100), Behaviour::Chaotic);
+        assert_eq!(identity.behaviour(100), Behaviour::Trivial);
+    }
+
+    #[test]
+    fn no_steps() {
+        let mut random = Random::new(25025069121927896);
+        let settings = Settings {
+            steps: 0,
+            ..Settings::default()
+        };
+        let c = complexity(&Rule::num_to_rule(633), &settings, &mut random);
+        assert_eq!(c.temporal_entropy, 0.0);
+        assert_eq!(c.compression, 0.0);
+    }
+}
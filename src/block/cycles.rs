@@ -0,0 +1,147 @@
+//! Полное разложение глобального отображения на циклы на маленьких кольцах.
+//!
+//! Глобальное отображение обратимого автомата это перестановка всех `2^size` состояний
+//! кольца, поэтому всё пространство состояний распадается на циклы. В отличие от выборки
+//! первых чисел, здесь перебираются все состояния, каждое посещается ровно один раз, так что
+//! время растёт как `2^size` и практично для `size` примерно до 27.
+
+use super::*;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Циклическая структура глобального отображения на кольце из `size` клеток.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CycleStructure<const N: usize> {
+    pub size: u32,
+    /// Длина цикла -> количество циклов такой длины.
+    pub histogram: BTreeMap<u64, u64>,
+    /// Состояния, которые за полный шаг переходят в свой поворот на целое число блоков, с
+    /// точностью до поворота: `(минимальный поворот, смещение в блоках)`, смещение как у
+    /// [`gliders::exact_period`]. Сюда входят и неподвижные точки со смещением 0.
+    pub fixed_up_to_translation: BTreeSet<(BlockField<N>, i32)>,
+}
+
+impl<const N: usize> CycleStructure<N> {
+    /// Раскладывает отображение `rule` на кольце из `size` клеток.
+    pub fn new<R: Evolution<N>>(rule: &R, size: u32) -> Self {
+        assert!(size <= 32);
+        let count = 1u64 << size;
+        let mut visited = vec![0u64; count.div_ceil(64) as usize];
+        let mut histogram = BTreeMap::new();
+        let mut fixed_up_to_translation = BTreeSet::new();
+        for start in 0..count {
+            if visited[(start / 64) as usize] & (1 << (start % 64)) != 0 {
+                continue;
+            }
+            let mut x = BlockField::<N>::new(start, size);
+            let mut length = 0;
+            loop {
+                visited[(x.val / 64) as usize] |= 1 << (x.val % 64);
+                let before = x;
+                rule.steps(&mut x);
+                length += 1;
+                if let Some(offset) = translation(before, x) {
+                    fixed_up_to_translation.insert((before.minimize(), offset));
+                }
+                if x.val == start {
+                    break;
+                }
+            }
+            *histogram.entry(length).or_insert(0) += 1;
+        }
+        Self {
+            size,
+            histogram,
+            fixed_up_to_translation,
+        }
+    }
+
+    /// Количество циклов.
+    pub fn cycles(&self) -> u64 {
+        self.histogram.values().sum()
+    }
+
+    /// Порядок глобальной перестановки, то есть НОК длин всех циклов. `None`, если он не
+    /// помещается в `u128`.
+    pub fn order(&self) -> Option<u128> {
+        self.histogram.keys().try_fold(1u128, |acc, length| {
+            let length = *length as u128;
+            (acc / gcd(acc, length)).checked_mul(length)
+        })
+    }
+}
+
+fn gcd(a: u128, b: u128) -> u128 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// смещение в блоках, если `after` это поворот `before`
+fn translation<const N: usize>(before: BlockField<N>, after: BlockField<N>) -> Option<i32> {
+    if before.val.count_ones() != after.val.count_ones() {
+        return None;
+    }
+    let blocks = (before.size / N as u32) as i32;
+    (-blocks / 2..=blocks / 2).find(|offset| after.rotate(offset * N as i32) == before)
+}
+
+/// Циклические структуры для всех размеров колец, кратных `N`, от `N` до `max_size`.
+pub fn cycle_structures<const N: usize, R: Evolution<N>>(
+    rule: &R,
+    max_size: u32,
+) -> Vec<CycleStructure<N>> {
+    (N as u32..=max_size)
+        .step_by(N)
+        .map(|size| CycleStructure::new(rule, size))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window3::{Field, Rule};
+
+    #[test]
+    fn identity() {
+        let cycles = CycleStructure::new(&Rule::RULE0, 12);
+        assert_eq!(cycles.histogram, BTreeMap::from([(1, 1 << 12)]));
+        assert_eq!(cycles.order(), Some(1));
+        assert!(cycles
+            .fixed_up_to_translation
+            .contains(&(Field::new(0b101, 12), 0)));
+    }
+
+    #[test]
+    fn same_as_period() {
+        for ni in [173, 633, 20000] {
+            let rule = Rule::num_to_rule(ni);
+            let cycles = CycleStructure::new(&rule, 15);
+            let total = cycles
+                .histogram
+                .iter()
+                .map(|(length, count)| length * count)
+                .sum::<u64>();
+            assert_eq!(total, 1 << 15);
+            let order = cycles.order().unwrap();
+            for val in [0, 1, 0b101, 0b110_011, 12345] {
+                let start = Field::new(val, 15);
+                let mut x = start;
+                let mut length = 0;
+                loop {
+                    rule.steps(&mut x);
+                    length += 1;
+                    if x == start {
+                        break;
+                    }
+                }
+                assert!(cycles.histogram.contains_key(&length));
+                assert_eq!(order % length as u128, 0);
+            }
+            for (x, offset) in &cycles.fixed_up_to_translation {
+                assert!(gliders::is_same_after(*x, &rule, 1, *offset));
+            }
+        }
+    }
+}
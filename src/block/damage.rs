@@ -0,0 +1,223 @@
+//! Распространение повреждений и световой конус.
+//!
+//! В случайном поле инвертируется одна клетка, и обе копии эволюционируют одним правилом.
+//! Разность копий (XOR) растёт внутри конуса: за полный шаг влияние уходит не дальше чем на
+//! `phases * (N - 1)` клеток в каждую сторону. Скорости левого и правого фронта и плотность
+//! разности внутри конуса, усреднённые по многим случайным полям, дают количественную меру
+//! хаотичности правила.
+
+use super::wide::WideField;
+use super::*;
+use crate::{draw_color_image, Random};
+
+/// Разность двух копий после одного полного шага.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Damage {
+    /// На сколько клеток разность ушла влево и вправо от инвертированной клетки. Как на
+    /// картинках [`show_damage`] и [`Evolution::show_field`], клетка 0 справа, так что влево
+    /// это к старшим клеткам.
+    pub left: u32,
+    pub right: u32,
+    /// Количество различающихся клеток.
+    pub count: u32,
+}
+
+/// Усреднённое по многим случайным полям распространение повреждений.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DamageProfile {
+    pub seeds: u64,
+    /// Средние [`Damage::left`], [`Damage::right`] и плотность разности внутри конуса
+    /// `[-left, right]` после каждого полного шага, начиная с первого.
+    pub left: Vec<f64>,
+    pub right: Vec<f64>,
+    pub density: Vec<f64>,
+}
+
+impl DamageProfile {
+    /// Скорость левого фронта в клетках за полный шаг.
+    pub fn left_speed(&self) -> f64 {
+        self.left
+            .last()
+            .map_or(0.0, |left| left / self.left.len() as f64)
+    }
+
+    /// Скорость правого фронта в клетках за полный шаг.
+    pub fn right_speed(&self) -> f64 {
+        self.right
+            .last()
+            .map_or(0.0, |right| right / self.right.len() as f64)
+    }
+
+    /// Плотность разности внутри конуса, усреднённая по второй половине шагов, когда фронты
+    /// уже разошлись.
+    pub fn mean_density(&self) -> f64 {
+        let tail = &self.density[self.density.len() / 2..];
+        tail.iter().sum::<f64>() / tail.len().max(1) as f64
+    }
+}
+
+/// Наибольшее расстояние, на которое влияние уходит за полный шаг в одну сторону.
+pub fn light_cone<const N: usize, R: Evolution<N>>(rule: &R) -> u32 {
+    rule.phases() * (N as u32 - 1)
+}
+
+// кольцо, на котором за `steps` шагов разность не успевает обойти круг
+fn ring_size<const N: usize, R: Evolution<N>>(rule: &R, steps: u64) -> u32 {
+    let n = N as u32;
+    (2 * light_cone(rule) * steps as u32 + 2 * n).div_ceil(n) * n
+}
+
+fn difference<const N: usize>(a: &WideField<N>, b: &WideField<N>, flip: u32) -> Damage {
+    let mut result = Damage {
+        left: 0,
+        right: 0,
+        count: 0,
+    };
+    for (i, (x, y)) in a.words().iter().zip(b.words()).enumerate() {
+        let mut diff = x ^ y;
+        while diff != 0 {
+            let pos = i as u32 * WideField::<N>::WORD_SIZE + diff.trailing_zeros();
+            diff &= diff - 1;
+            result.count += 1;
+            result.left = result.left.max(pos.saturating_sub(flip));
+            result.right = result.right.max(flip.saturating_sub(pos));
+        }
+    }
+    result
+}
+
+/// Инвертирует клетку `flip` поля `x` и возвращает разность копий после каждого из `steps`
+/// полных шагов. Кольцо должно быть достаточно большим, чтобы разность не обошла его.
+pub fn damage<const N: usize, R: Evolution<N>>(
+    rule: &R,
+    x: &WideField<N>,
+    flip: u32,
+    steps: u64,
+) -> Vec<Damage> {
+    let mut a = x.clone();
+    let mut b = x.clone();
+    b.set(flip, !b.index(flip));
+    (0..steps)
+        .map(|_| {
+            rule.steps(&mut a);
+            rule.steps(&mut b);
+            difference(&a, &b, flip)
+        })
+        .collect()
+}
+
+/// Распространение повреждений за `steps` шагов, усреднённое по `seeds` случайным полям.
+pub fn damage_spreading<const N: usize, R: Evolution<N>>(
+    rule: &R,
+    steps: u64,
+    seeds: u64,
+    random: &mut Random,
+) -> DamageProfile {
+    let size = ring_size(rule, steps);
+    let mut result = DamageProfile {
+        seeds,
+        left: vec![0.0; steps as usize],
+        right: vec![0.0; steps as usize],
+        density: vec![0.0; steps as usize],
+    };
+    for _ in 0..seeds {
+        let x = WideField::random(random, size, 0.5);
+        for (i, d) in damage(rule, &x, size / 2, steps).iter().enumerate() {
+            result.left[i] += d.left as f64 / seeds as f64;
+            result.right[i] += d.right as f64 / seeds as f64;
+            result.density[i] += d.count as f64 / (d.left + d.right + 1) as f64 / seeds as f64;
+        }
+    }
+    result
+}
+
+/// Рисует `steps` фаз эволюции случайного поля с одной инвертированной клеткой посередине в
+/// `data/img_{img_name}/`: клетки первой копии чёрные, различающиеся клетки красные.
+/// Возвращает путь относительно `data/`.
+pub fn show_damage<const N: usize, R: Evolution<N>>(
+    rule: &R,
+    steps: u64,
+    random: &mut Random,
+    ni: u64,
+    img_name: &str,
+) -> String {
+    let size = ring_size(rule, steps.div_ceil(rule.phases() as u64));
+    let mut a = WideField::<N>::random(random, size, 0.5);
+    let mut b = a.clone();
+    b.set(size / 2, !b.index(size / 2));
+    let row = |a: &WideField<N>, b: &WideField<N>| {
+        (0..size)
+            .rev()
+            .map(|i| match (a.index(i), b.index(i)) {
+                (x, y) if x != y => 2,
+                (x, _) => x as u8,
+            })
+            .collect::<Vec<_>>()
+    };
+    let mut result = vec![row(&a, &b)];
+    for _ in 0..steps / rule.phases() as u64 {
+        for phase in 0..rule.phases() {
+            rule.phase(&mut a, phase);
+            rule.phase(&mut b, phase);
+            result.push(row(&a, &b));
+        }
+    }
+    std::fs::create_dir_all(format!("data/img_{}", img_name)).unwrap();
+    let name = format!("img_{}/{}_{}_{}.png", img_name, size, steps, ni);
+    draw_color_image(&format!("data/{}", name), result);
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window3::Rule;
+
+    #[test]
+    fn identity() {
+        let mut random = Random::new(25025069121927896);
+        let profile = damage_spreading(&Rule::RULE0, 20, 5, &mut random);
+        assert_eq!(profile.left_speed(), 0.0);
+        assert_eq!(profile.right_speed(), 0.0);
+        assert_eq!(profile.mean_density(), 1.0);
+    }
+
+    #[test]
+    fn one_sided() {
+        // поворот каждого блока на клетку вверх: за полный шаг клетка уходит на три влево
+        let rule = Rule::from_permutation(
+            &(0..8)
+                .map(|b| ((b << 1) | (b >> 2)) & 7)
+                .collect::<Vec<_>>(),
+        );
+        let mut random = Random::new(25025069121927896);
+        let x = WideField::<3>::random(&mut random, ring_size(&rule, 20), 0.5);
+        for (step, d) in damage(&rule, &x, x.size() / 2, 20).iter().enumerate() {
+            assert_eq!((d.left, d.right, d.count), (3 * (step as u32 + 1), 0, 1));
+        }
+        let profile = damage_spreading(&rule, 20, 5, &mut random);
+        assert_eq!((profile.left_speed(), profile.right_speed()), (3.0, 0.0));
+    }
+
+    #[test]
+    fn inside_light_cone() {
+        let mut random = Random::new(25025069121927896);
+        for ni in [173, 633, 876, 20000] {
+            let rule = Rule::num_to_rule(ni);
+            let cone = light_cone(&rule);
+            let x = WideField::<3>::random(&mut random, ring_size(&rule, 30), 0.5);
+            for (step, d) in damage(&rule, &x, x.size() / 2, 30).iter().enumerate() {
+                // обратимость: разность не исчезает
+                assert!(d.count > 0);
+                assert!(d.left <= cone * (step as u32 + 1));
+                assert!(d.right <= cone * (step as u32 + 1));
+            }
+            let profile = damage_spreading(&rule, 30, 10, &mut random);
+            assert!(profile.left_speed() <= cone as f64);
+            assert!(profile.right_speed() <= cone as f64);
+            // все эти правила хаотичны
+            assert!(profile.left_speed() > 0.5 && profile.right_speed() > 0.5);
+            assert!(profile.mean_density() > 0.0 && profile.mean_density() <= 1.0);
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! Глайдеры в периодическом эфире.
+//!
+//! Эфир это непустой фон, периодический и в пространстве, и во времени: кольцо из нескольких
+//! блоков, которое через `period` шагов переходит в свой поворот. Сначала перебираются все
+//! такие фоны маленькой ширины, затем на каждом из них ищутся локализованные дефекты: поле
+//! [`InfiniteField`] с этим фоном, в котором несколько клеток инвертированы, и которое через
+//! некоторое время повторяет свою форму относительно фона.
+
+use super::infinite::{self, InfiniteField};
+use super::*;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Пространственно и временно периодический фон.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Ether<const N: usize> {
+    /// Минимальная форма по всем поворотам и фазам.
+    pub background: BlockField<N>,
+    /// Через `period` шагов фон переходит в себя, сдвинутый на `offset` блоков, как у
+    /// [`gliders::exact_period`].
+    pub period: u64,
+    pub offset: i32,
+}
+
+impl<const N: usize> Ether<N> {
+    /// Количество шагов, после которого фон совпадает с собой без поворота.
+    pub fn recurrence(&self) -> u64 {
+        let blocks = (self.background.size() / N as u32) as i32;
+        let mut count = 1;
+        while (self.offset * count).rem_euclid(blocks) != 0 {
+            count += 1;
+        }
+        self.period * count as u64
+    }
+}
+
+// поле не повторяет само себя с меньшим периодом в блоках
+fn is_primitive<const N: usize>(x: BlockField<N>) -> bool {
+    let blocks = x.size() / N as u32;
+    (1..blocks)
+        .filter(|d| blocks.is_multiple_of(*d))
+        .all(|d| x.rotate_left(d * N as u32) != x)
+}
+
+/// Находит все непустые эфиры шириной до `max_blocks` блоков с периодом до `max_period`.
+/// Фоны, которые повторяют фон меньшей ширины, пропускаются.
+pub fn find_ethers<const N: usize, R: Evolution<N>>(
+    rule: &R,
+    max_blocks: u32,
+    max_period: u64,
+) -> Vec<Ether<N>> {
+    let mut result = BTreeSet::new();
+    for blocks in 1..=max_blocks {
+        let size = blocks * N as u32;
+        for val in 1..=low_mask(size) {
+            let x = BlockField::<N>::new(val, size);
+            if !is_primitive(x) {
+                continue;
+            }
+            if let Some((period, offset)) = gliders::exact_period(x, rule, Some(max_period)) {
+                result.insert(Ether {
+                    background: gliders::minimize2(x, rule, period),
+                    period,
+                    offset,
+                });
+            }
+        }
+    }
+    result.into_iter().collect()
+}
+
+/// Дефект в эфире: клетки `defect` инвертированы начиная с координаты 0.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct EtherGlider<const N: usize> {
+    pub ether: Ether<N>,
+    pub defect: u64,
+    /// Период и смещение в блоках относительно неподвижных координат, как у
+    /// [`infinite::period`].
+    pub period: u64,
+    pub offset: i32,
+}
+
+/// Поле с фоном `background`, в котором инвертированы клетки `defect`.
+pub fn with_defect<const N: usize>(background: BlockField<N>, defect: u64) -> InfiniteField<N> {
+    let mut x = InfiniteField::new(background);
+    for pos in 0..occupied_size(defect) as i64 {
+        if (defect >> pos) & 1 != 0 {
+            x.set(pos, !x.get(pos));
+        }
+    }
+    x
+}
+
+/// Находит глайдеры и осцилляторы в эфире `ether`, перебирая дефекты до `max_count`. Дефекты,
+/// которые за `max_steps` шагов не повторили форму, пропускаются. Из дефектов, которые дают
+/// одну и ту же форму с точностью до фазы, остаётся первый.
+pub fn find_ether_gliders<const N: usize, R: Evolution<N>>(
+    rule: &R,
+    ether: &Ether<N>,
+    max_count: u64,
+    max_steps: u64,
+) -> Vec<EtherGlider<N>> {
+    let mut found = BTreeMap::new();
+    for defect in 1..max_count {
+        let x = with_defect(ether.background, defect);
+        let (period, offset) = match infinite::period(x.clone(), rule, max_steps) {
+            Some(found) => found,
+            None => continue,
+        };
+        // форма выровнена по периоду фона, поэтому пара (фон, форма) не зависит от положения
+        let mut y = x;
+        let mut key = (y.background(), y.shape().1);
+        for _ in 1..period {
+            rule.steps(&mut y);
+            key = key.min((y.background(), y.shape().1));
+        }
+        found.entry(key).or_insert(EtherGlider {
+            ether: *ether,
+            defect,
+            period,
+            offset,
+        });
+    }
+    let mut result = found.into_values().collect::<Vec<_>>();
+    result.sort();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window3::Rule;
+
+    #[test]
+    fn identity_ethers() {
+        // 7 фонов из одного блока и 28 классов из 56 непериодичных фонов из двух блоков
+        let ethers = find_ethers(&Rule::RULE0, 2, 10);
+        assert_eq!(ethers.len(), 7 + 28);
+        assert!(ethers.iter().all(|e| e.period == 1 && e.offset == 0));
+
+        let gliders = find_ether_gliders(&Rule::RULE0, &ethers[0], 8, 10);
+        assert!(gliders.iter().all(|g| g.period == 1 && g.offset == 0));
+    }
+
+    #[test]
+    fn inverted_glider() {
+        // на заполненном фоне правило с инвертированными цветами ведёт себя как 876 на пустом
+        let rule = Rule::num_to_rule(876);
+        let inverted = rule.invert_color();
+        let full = Ether {
+            background: BlockField::new(0b111, 3),
+            period: 1,
+            offset: 0,
+        };
+        assert!(find_ethers(&inverted, 1, 10).contains(&full));
+        assert_eq!(full.recurrence(), 1);
+        let gliders = find_ether_gliders(&inverted, &full, 4, 20);
+        assert_eq!(
+            gliders
+                .iter()
+                .map(|g| (g.defect, g.period, g.offset))
+                .collect::<Vec<_>>(),
+            gliders::get_gliders(&rule, 4, Some(200), false)
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,676 @@
+//! Поиск глайдеров, осцилляторов и глайдерных ружей на пустом поле.
+
+use super::*;
+use indicatif::ProgressBar;
+use indicatif::ProgressStyle;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Циклическое поле, с которым работают поиск периодов и глайдеров. Поле поворачивается на
+/// целое число позиций: у блочных полей позиция это блок, у полей без блоков клетка. Смещения
+/// тоже считаются в позициях.
+pub trait Ring: Copy + Eq {
+    /// Форма поля для сравнения и поиска, не зависит от размера кольца.
+    type Key: Copy + Ord + Hash;
+    /// Количество клеток в позиции.
+    const CELLS: u32;
+    /// Наибольшее количество позиций.
+    const MAX_POSITIONS: u32;
+
+    fn key(&self) -> Self::Key;
+
+    fn positions(&self) -> u32;
+
+    /// Количество клеток до последней занятой включительно.
+    fn occupied_cells(&self) -> u32;
+
+    /// Поле, в котором оставлены только первые `cells` клеток.
+    fn prefix(&self, cells: u32) -> Self;
+
+    /// То же содержимое на кольце из `positions` позиций.
+    fn resize(&self, positions: u32) -> Self;
+
+    /// Поворот на `count` позиций, как [`BlockField::rotate`] на `count` блоков.
+    fn shift(self, count: i32) -> Self;
+
+    /// Поворот с наименьшим ключом.
+    fn canonical(self) -> Self;
+
+    /// Количество позиций до последней занятой включительно.
+    fn occupied(&self) -> u32 {
+        self.occupied_cells().div_ceil(Self::CELLS)
+    }
+}
+
+/// Эволюция циклического поля `F` для поиска периодов и глайдеров.
+pub trait Dynamics<F> {
+    /// Полный шаг.
+    fn advance(&self, x: &mut F);
+
+    /// На сколько позиций удлиняется кольцо при каждой проверке в [`is_this_glider`].
+    fn margin(&self) -> u32 {
+        1
+    }
+}
+
+impl<const N: usize> Ring for BlockField<N> {
+    type Key = u64;
+    const CELLS: u32 = N as u32;
+    const MAX_POSITIONS: u32 = BlockField::<N>::MAX_SIZE / N as u32;
+
+    fn key(&self) -> u64 {
+        self.val
+    }
+
+    fn positions(&self) -> u32 {
+        self.size / N as u32
+    }
+
+    fn occupied_cells(&self) -> u32 {
+        occupied_size(self.val)
+    }
+
+    fn prefix(&self, cells: u32) -> Self {
+        BlockField::new(self.val & low_mask(cells), self.size)
+    }
+
+    fn resize(&self, positions: u32) -> Self {
+        BlockField::new(self.val, positions * N as u32)
+    }
+
+    fn shift(self, count: i32) -> Self {
+        self.rotate(count * N as i32)
+    }
+
+    fn canonical(self) -> Self {
+        self.minimize()
+    }
+}
+
+impl<const N: usize, R: Evolution<N>> Dynamics<BlockField<N>> for R {
+    fn advance(&self, x: &mut BlockField<N>) {
+        self.steps(x);
+    }
+}
+
+/// Находит период и смещение в позициях глайдера, осциллятора или статичной картинки без
+/// ограничения на смещение. На каждом шаге поле сравнивается с исходным с точностью до
+/// поворота по минимальному повороту, поэтому первое совпадение это настоящий период: правило
+/// обратимо, и поле обязательно вернётся к повороту исходного. Смещение выбирается наименьшим
+/// по модулю, при равенстве отрицательное, и на кольце определено с точностью до его размера.
+/// `max_steps` ограничивает количество шагов, `None` означает шагать до возврата.
+pub fn exact_period<F: Ring, R: Dynamics<F>>(
+    mut x: F,
+    rule: &R,
+    max_steps: Option<u64>,
+) -> Option<(u64, i32)> {
+    let y = x;
+    let canonical = y.canonical();
+    let positions = x.positions() as i32;
+    for period in 1..=max_steps.unwrap_or(u64::MAX) {
+        rule.advance(&mut x);
+        if x.canonical() == canonical {
+            let offset = (0..=positions / 2)
+                .flat_map(|offset| [-offset, offset])
+                .find(|offset| x.shift(*offset) == y)
+                .unwrap();
+            return Some((period, offset));
+        }
+    }
+    None
+}
+
+pub fn is_same_after<F: Ring, R: Dynamics<F>>(mut x: F, rule: &R, steps: u64, offset: i32) -> bool {
+    let y = x;
+    for _ in 0..steps {
+        rule.advance(&mut x);
+    }
+    x.shift(offset) == y
+}
+
+/// Находит минимальную форму глайдера среди всех его фаз.
+pub fn minimize2<F: Ring, R: Dynamics<F>>(mut x: F, rule: &R, period: u64) -> F {
+    let mut my_min = x.canonical();
+    for _ in 0..period {
+        rule.advance(&mut x);
+        my_min = std::cmp::min_by_key(my_min, x.canonical(), |x| x.key());
+    }
+    my_min
+}
+
+fn size_round(x: u32, n: u32) -> u32 {
+    x / n + (!x.is_multiple_of(n) as u32)
+}
+
+/// Проверяет, что за `period` шагов все связные куски паттерна происходят из его самой первой клетки.
+pub fn check_reach_everything<const N: usize, R: Evolution<N>>(
+    mut x: BlockField<N>,
+    rule: &R,
+    period: u64,
+) -> bool {
+    let pos_start = (|| {
+        for i in 0..x.size {
+            if x.index(i) {
+                return i;
+            }
+        }
+        unreachable!()
+    })();
+
+    let mut reached = BlockField::<N>::new(1 << pos_start, x.size);
+
+    let mut prev_x = x;
+
+    let phases = rule.phases() as u64;
+    for step in 0..=period * phases {
+        let mut reached2 = BlockField::<N>::new(0, x.size);
+
+        let pos_start = (|| {
+            for i in 0..x.size {
+                if !x.index(i) {
+                    return i;
+                }
+            }
+            unreachable!()
+        })();
+
+        let mut state = 0;
+        let mut current = 0;
+        let mut used = false;
+        for i in (0..x.size).map(|i| (pos_start + i) % x.size) {
+            if x.index(i) {
+                if state == 0 {
+                    state = 1;
+                    used = reached.index(i);
+                    current = 1 << i;
+                } else {
+                    used |= reached.index(i);
+                    current |= 1 << i;
+                }
+            } else if state == 1 {
+                state = 0;
+            }
+            if used {
+                reached2.val |= current;
+            }
+        }
+
+        reached = reached2;
+
+        prev_x = x;
+
+        rule.phase(&mut x, (step % phases) as u32);
+    }
+
+    reached.val == prev_x.val
+}
+
+/// В цикличном массиве находит в каком месте начинается данный паттерн и его длину. Для этого находит нули максимального размера чтобы считать их пустым полем.
+pub fn find_pattern_start<F: Ring>(x: F) -> i32 {
+    let mut my_min = x.key();
+    let mut min_offset: i32 = 0;
+
+    for i in 1..=size_round(x.positions(), 2) as i32 {
+        let new = x.shift(-i);
+        if new.key() < my_min {
+            my_min = new.key();
+            min_offset = -i;
+        }
+
+        let new = x.shift(i);
+        if new.key() < my_min {
+            my_min = new.key();
+            min_offset = i;
+        }
+    }
+
+    min_offset
+}
+
+// минимальная форма -> (период, следующая минимальная форма, смещение)
+type Used<F> = HashMap<<F as Ring>::Key, (u64, F, i32)>;
+
+fn add_used<F: Ring, R: Dynamics<F>>(mut x: F, period: u64, rule: &R, used: &mut Used<F>) {
+    for _ in 0..=period {
+        let start_offset = find_pattern_start(x);
+        let prev = x;
+        rule.advance(&mut x);
+        used.insert(
+            prev.canonical().key(),
+            (period, x.canonical(), find_pattern_start(x) - start_offset),
+        );
+    }
+}
+
+// предполагается что x уже минимизировано
+fn check_used<F: Ring, R: Dynamics<F>>(mut x: F, period: u64, rule: &R, used: &Used<F>) -> bool {
+    let size = x.occupied_cells();
+    let y = x;
+    'outer: for i in 1..=size {
+        x = y;
+        let possible_glider = x.prefix(i).key();
+        if let Some((period_local, mut next, mut offset_local)) =
+            used.get(&possible_glider).cloned()
+        {
+            if period.is_multiple_of(period_local) {
+                let mut offset = offset_local;
+                for _ in 0..period {
+                    rule.advance(&mut x);
+                    let now_sub = x.shift(offset).prefix(next.occupied_cells()).key();
+                    if now_sub != next.key() {
+                        continue 'outer;
+                    }
+
+                    let (_, b, c) = *used.get(&next.key()).unwrap();
+                    next = b;
+                    offset_local = c;
+
+                    offset += offset_local;
+                }
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Уникальные глайдеры одного правила, найденные по очереди, как в [`find_gliders`].
+pub struct Catalogue<F: Ring> {
+    used: Used<F>,
+    /// Ключи минимальных форм, периоды и смещения.
+    pub gliders: BTreeSet<(F::Key, u64, i32)>,
+}
+
+impl<F: Ring> Catalogue<F> {
+    pub fn new() -> Self {
+        Self {
+            used: HashMap::new(),
+            gliders: BTreeSet::new(),
+        }
+    }
+
+    /// Добавляет результат [`is_this_glider`], если он не составлен из уже найденных глайдеров.
+    pub fn insert<R: Dynamics<F>>(&mut self, rule: &R, min: F, period: u64, offset: i32) {
+        if !check_used(min, period, rule, &self.used) {
+            add_used(min, period, rule, &mut self.used);
+            self.gliders.insert((min.key(), period, offset));
+        }
+    }
+}
+
+impl<F: Ring> Default for Catalogue<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Определяет является ли данный паттерн глайдером и находит его минимальную форму. Период
+/// ищется не дольше `max_period` шагов, как в [`exact_period`].
+pub fn is_this_glider<F: Ring, R: Dynamics<F>>(
+    x: F,
+    rule: &R,
+    max_period: Option<u64>,
+) -> Option<(F, u64, i32)> {
+    let size = x.occupied();
+    let margin = rule.margin();
+    if size + 9 * margin > F::MAX_POSITIONS {
+        return None;
+    }
+    let ring = |count: u32| x.resize(size + count * margin);
+    // на самом большом кольце, чтобы смещение больше половины маленького кольца было однозначным
+    let (period, offset) = exact_period(ring(9), rule, max_period)?;
+    for i in [3, 4, 5, 6] {
+        if !is_same_after(ring(i), rule, period, offset) {
+            return None;
+        }
+    }
+
+    Some((minimize2(ring(5), rule, period), period, offset))
+}
+
+/// Находит всех уникальных глайдеров и осцилляторов среди полей `candidate(1), ...,
+/// candidate(max_count - 1)`. Паттерны, составленные из уже найденных глайдеров, пропускаются.
+/// Период каждого поля ищется не дольше `max_period` шагов. Возвращает ключи минимальных форм.
+pub fn find_gliders<F: Ring, R: Dynamics<F>>(
+    rule: &R,
+    max_count: u64,
+    candidate: impl Fn(u64) -> F,
+    max_period: Option<u64>,
+    use_progress: bool,
+) -> BTreeSet<(F::Key, u64, i32)> {
+    let mut catalogue = Catalogue::new();
+    let progress = ProgressBar::new(max_count).with_style(
+        ProgressStyle::default_bar()
+            .template("[elapsed: {elapsed:>6} | remaining: {eta:>6}] {wide_bar}"),
+    );
+    for x in 1..max_count {
+        if use_progress {
+            progress.inc(1);
+        }
+        if let Some((min, period, offset)) = is_this_glider(candidate(x), rule, max_period) {
+            catalogue.insert(rule, min, period, offset);
+        }
+    }
+    catalogue.gliders
+}
+
+/// Находит всех уникальных глайдеров и осцилляторов для данного правила, перебирая все числа до max_count и переводя их в битовое представление.
+/// Период каждого числа ищется не дольше `max_period` шагов.
+pub fn get_gliders<const N: usize, R: Evolution<N>>(
+    rule: &R,
+    max_count: u64,
+    max_period: Option<u64>,
+    use_progress: bool,
+) -> BTreeSet<(u64, u64, i32)> {
+    find_gliders(
+        rule,
+        max_count,
+        |x| BlockField::<N>::new(x, BlockField::<N>::MAX_SIZE),
+        max_period,
+        use_progress,
+    )
+}
+
+/// Проверяет, является ли паттерн глайдерным ружьём. Возвращает период ружья и минимальную форму, период и смещение выпускаемого глайдера.
+pub fn is_this_glider_gun<const N: usize, R: Evolution<N>>(
+    val: u64,
+    rule: &R,
+) -> Option<(u64, u64, u64, i32)> {
+    let max_size = BlockField::<N>::MAX_SIZE;
+    let pat = low_mask(occupied_size_n(val, N as u32));
+    let mut x = BlockField::new(val, max_size - N as u32);
+    rule.steps(&mut x);
+    let mut count = 1;
+
+    while x.val != val && count < 15 {
+        if (x.val & pat) == val && (x.val & !pat) != 0 {
+            let x_without_gun = BlockField::<N>::new(x.val & !pat, x.size).minimize();
+
+            let mut x0 = BlockField::new(x.val, max_size - N as u32);
+            rule.steps_count(&mut x0, count);
+            if (x0.val & pat) == val && (x0.val & !pat) != 0 {
+                let x0_without_gun = BlockField::<N>::new(x0.val & !pat, x0.size).minimize();
+
+                let mut x1 = BlockField::new(val, max_size);
+                rule.steps_count(&mut x1, count);
+                if (x1.val & pat) == val && (x1.val & !pat) != 0 {
+                    let x1_without_gun = BlockField::<N>::new(x1.val & !pat, x1.size).minimize();
+
+                    if x_without_gun.val == x1_without_gun.val {
+                        if let Some(((min, period, offset), (_, period0, offset0))) =
+                            is_this_glider(x_without_gun, rule, Some(count)).zip(is_this_glider(
+                                x0_without_gun,
+                                rule,
+                                Some(count),
+                            ))
+                        {
+                            if period == period0
+                                && offset == offset0
+                                && offset != 0
+                                && period <= count
+                            {
+                                return Some((count, min.val, period, offset));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        rule.steps(&mut x);
+        count += 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window3::{Field, Rule};
+
+    #[test]
+    fn exact_periods() {
+        let rule = Rule::num_to_rule(633);
+        for (val, found) in [
+            (0b1, Some((2, 0))),
+            (0b101, Some((4, 1))),
+            (0b1010, Some((2, -1))),
+            (0b101101, Some((13, 1))),
+        ] {
+            assert_eq!(exact_period(Field::new(val, 30), &rule, None), found);
+        }
+        // глайдер с большим периодом из collide_gliders_633
+        let x = Field::new(0b11010111101101, 63).centralize();
+        let (p, offset) = exact_period(x, &rule, None).unwrap();
+        assert_eq!(p, 43);
+        assert!(is_same_after(x, &rule, p, offset));
+        assert_eq!(exact_period(x, &rule, Some(42)), None);
+        assert!(is_this_glider(Field::new(0b11010111101101, 63), &rule, Some(42)).is_none());
+        assert_eq!(
+            is_this_glider(Field::new(0b11010111101101, 63), &rule, Some(43)).map(|(_, p, _)| p),
+            Some(43)
+        );
+
+        // пять шагов 876 за один: глайдер из одной клетки смещается на 5 блоков
+        let rule = Rule::num_to_rule(876);
+        let fast = super::schedule::Schedule::with_offsets(&rule, &[0, 1, 2].repeat(5));
+        let x = Field::new(0b1, 63);
+        assert_eq!(exact_period(x, &rule, None), Some((1, 1)));
+        assert_eq!(exact_period(x, &fast, None), Some((1, 5)));
+        let four = super::schedule::Schedule::with_offsets(&rule, &[0, 1, 2].repeat(4));
+        assert_eq!(
+            is_this_glider(Field::new(0b1, 63), &four, None).map(|(_, p, offset)| (p, offset)),
+            Some((1, 4))
+        );
+    }
+
+    #[test]
+    fn glider_guns() {
+        color_backtrace::install();
+
+        let rule = Rule::num_to_rule(116);
+        assert_eq!(is_this_glider_gun(7, &rule), None);
+
+        let rule = Rule::num_to_rule(160);
+        assert_eq!(is_this_glider_gun(1, &rule), None);
+        assert_eq!(is_this_glider_gun(305, &rule), None);
+        assert_eq!(is_this_glider_gun(437, &rule), None);
+        assert_eq!(is_this_glider_gun(601, &rule), None);
+
+        let rule = Rule::num_to_rule(170);
+        assert_eq!(is_this_glider_gun(16781, &rule), None);
+
+        let rule = Rule::num_to_rule(173);
+        assert_eq!(is_this_glider_gun(7, &rule), None);
+        assert_eq!(is_this_glider_gun(59, &rule), None);
+        assert_eq!(is_this_glider_gun(484, &rule), None);
+        assert_eq!(is_this_glider_gun(9403, &rule), None);
+        assert_eq!(is_this_glider_gun(1250, &rule), Some((5, 33, 2, -1)));
+        assert_eq!(is_this_glider_gun(3813, &rule), Some((5, 33, 2, -1)));
+        assert_eq!(is_this_glider_gun(3911, &rule), Some((5, 33, 2, -1)));
+
+        let rule = Rule::num_to_rule(194);
+        assert_eq!(is_this_glider_gun(2, &rule), Some((2, 1, 2, -1)));
+        assert_eq!(is_this_glider_gun(5, &rule), Some((2, 1, 2, -1)));
+        assert_eq!(is_this_glider_gun(6, &rule), Some((2, 1, 2, -1)));
+        assert_eq!(is_this_glider_gun(12, &rule), Some((2, 1, 2, -1)));
+        assert_eq!(is_this_glider_gun(13, &rule), Some((2, 1, 2, -1)));
+        assert_eq!(is_this_glider_gun(13, &rule), Some((2, 1, 2, -1)));
+        assert_eq!(is_this_glider_gun(18, &rule), Some((2, 1, 2, -1)));
+        assert_eq!(is_this_glider_gun(50, &rule), Some((2, 1, 2, -1)));
+        assert_eq!(is_this_glider_gun(146, &rule), Some((2, 1, 2, -1)));
+        assert_eq!(is_this_glider_gun(1170, &rule), Some((2, 1, 2, -1)));
+        assert_eq!(is_this_glider_gun(9362, &rule), Some((2, 1, 2, -1)));
+
+        let rule = Rule::num_to_rule(207);
+        assert_eq!(is_this_glider_gun(52, &rule), None);
+        assert_eq!(is_this_glider_gun(244, &rule), None);
+        assert_eq!(is_this_glider_gun(3328, &rule), None);
+        assert_eq!(is_this_glider_gun(14068, &rule), None);
+
+        let rule = Rule::num_to_rule(208);
+        assert_eq!(is_this_glider_gun(505, &rule), None);
+        assert_eq!(is_this_glider_gun(862, &rule), None);
+        assert_eq!(is_this_glider_gun(3328, &rule), None);
+        assert_eq!(is_this_glider_gun(14068, &rule), None);
+
+        let rule = Rule::num_to_rule(214);
+        assert_eq!(is_this_glider_gun(32, &rule), None);
+        assert_eq!(is_this_glider_gun(220, &rule), None);
+
+        let rule = Rule::num_to_rule(218);
+        assert_eq!(is_this_glider_gun(2, &rule), Some((2, 1, 2, -1)));
+
+        let rule = Rule::num_to_rule(219);
+        assert_eq!(is_this_glider_gun(99, &rule), Some((2, 1, 2, -1)));
+
+        let rule = Rule::num_to_rule(268);
+        assert_eq!(is_this_glider_gun(3, &rule), Some((2, 1, 2, -1)));
+
+        let rule = Rule::num_to_rule(275);
+        assert_eq!(is_this_glider_gun(81920, &rule), None);
+
+        let rule = Rule::num_to_rule(369);
+        assert_eq!(is_this_glider_gun(6, &rule), None);
+
+        let rule = Rule::num_to_rule(627);
+        assert_eq!(is_this_glider_gun(1699, &rule), Some((5, 33, 2, -1)));
+        assert_eq!(is_this_glider_gun(3237, &rule), Some((5, 33, 2, -1)));
+
+        let rule = Rule::num_to_rule(876);
+        assert_eq!(is_this_glider_gun(2, &rule), Some((1, 1, 1, 1)));
+        assert_eq!(is_this_glider_gun(3, &rule), Some((1, 1, 1, 1)));
+        assert_eq!(is_this_glider_gun(4, &rule), Some((1, 1, 1, 1)));
+        assert_eq!(is_this_glider_gun(10, &rule), Some((1, 1, 1, 1)));
+        assert_eq!(is_this_glider_gun(11, &rule), Some((1, 1, 1, 1)));
+    }
+
+    #[test]
+    fn test1() {
+        color_backtrace::install();
+
+        let rule = Rule::num_to_rule(633);
+        let mut used = HashMap::new();
+
+        add_used(Field::new(1, 9), 2, &rule, &mut used);
+        add_used(Field::new(2, 9), 2, &rule, &mut used);
+        add_used(Field::new(4, 9), 2, &rule, &mut used);
+        assert!(!check_used(Field::new(3, 30), 13, &rule, &used));
+        assert!(!check_used(Field::new(5, 30), 13, &rule, &used));
+        assert!(!check_used(Field::new(10, 30), 13, &rule, &used));
+        assert!(!check_used(Field::new(45, 30), 13, &rule, &used));
+        assert!(!check_used(Field::new(765, 30), 13, &rule, &used));
+        assert!(!check_used(Field::new(1215, 30), 28, &rule, &used));
+        assert!(!check_used(Field::new(6125, 30), 13, &rule, &used));
+        assert!(check_used(Field::new(4, 30), 4, &rule, &used));
+        assert!(check_used(Field::new(17, 30), 4, &rule, &used));
+        assert!(check_used(Field::new(23, 30), 4, &rule, &used));
+
+        add_used(Field::new(3, 9), 2, &rule, &mut used);
+        add_used(Field::new(6, 9), 2, &rule, &mut used);
+        add_used(Field::new(12, 9), 2, &rule, &mut used);
+        assert!(!check_used(Field::new(5, 30), 13, &rule, &used));
+        assert!(!check_used(Field::new(10, 30), 13, &rule, &used));
+        assert!(!check_used(Field::new(45, 30), 13, &rule, &used));
+        assert!(!check_used(Field::new(765, 30), 13, &rule, &used));
+        assert!(!check_used(Field::new(1215, 30), 28, &rule, &used));
+        assert!(!check_used(Field::new(6125, 30), 13, &rule, &used));
+        assert!(check_used(Field::new(12, 30), 4, &rule, &used));
+        assert!(check_used(Field::new(19, 30), 4, &rule, &used));
+        assert!(check_used(Field::new(27, 30), 4, &rule, &used));
+
+        add_used(Field::new(5, 12), 4, &rule, &mut used);
+        assert!(!check_used(Field::new(45, 30), 13, &rule, &used));
+        assert!(!check_used(Field::new(765, 30), 13, &rule, &used));
+        assert!(!check_used(Field::new(1215, 30), 28, &rule, &used));
+        assert!(!check_used(Field::new(6125, 30), 13, &rule, &used));
+        assert!(check_used(Field::new(2565, 30), 4, &rule, &used));
+        assert!(check_used(Field::new(2805, 30), 4, &rule, &used));
+        assert!(check_used(Field::new(10405, 30), 4, &rule, &used));
+        assert!(check_used(Field::new(89775, 30), 4, &rule, &used));
+
+        add_used(Field::new(10, 12), 2, &rule, &mut used);
+        assert!(!check_used(Field::new(45, 30), 13, &rule, &used));
+        assert!(!check_used(Field::new(765, 30), 13, &rule, &used));
+        assert!(!check_used(Field::new(1215, 30), 28, &rule, &used));
+        assert!(!check_used(Field::new(6125, 30), 13, &rule, &used));
+        assert!(check_used(Field::new(490, 30), 2, &rule, &used));
+        assert!(check_used(Field::new(650, 30), 2, &rule, &used));
+        assert!(check_used(Field::new(3850, 30), 2, &rule, &used));
+
+        add_used(Field::new(45, 15), 13, &rule, &mut used);
+        assert!(!check_used(Field::new(765, 30), 13, &rule, &used));
+        assert!(!check_used(Field::new(1215, 30), 28, &rule, &used));
+        assert!(!check_used(Field::new(6125, 30), 13, &rule, &used));
+        assert!(check_used(Field::new(23085, 30), 13, &rule, &used));
+        assert!(check_used(Field::new(23275, 30), 13, &rule, &used));
+        assert!(check_used(Field::new(92255, 30), 13, &rule, &used));
+    }
+
+    #[test]
+    fn reach_everything() {
+        color_backtrace::install();
+
+        let rule = Rule::num_to_rule(633);
+        assert!(check_reach_everything(Field::new(5, 30), &rule, 4));
+        assert!(check_reach_everything(Field::new(10, 30), &rule, 2));
+        assert!(check_reach_everything(Field::new(45, 30), &rule, 13));
+        assert!(!check_reach_everything(Field::new(165, 30), &rule, 4));
+        assert!(!check_reach_everything(Field::new(175, 30), &rule, 4));
+        assert!(!check_reach_everything(Field::new(325, 30), &rule, 4));
+        assert!(!check_reach_everything(Field::new(490, 30), &rule, 2));
+        assert!(!check_reach_everything(Field::new(650, 30), &rule, 2));
+        assert!(check_reach_everything(Field::new(765, 30), &rule, 13));
+        assert!(!check_reach_everything(Field::new(965, 30), &rule, 4));
+        assert!(check_reach_everything(Field::new(1215, 30), &rule, 28));
+        assert!(!check_reach_everything(Field::new(1285, 30), &rule, 4));
+        assert!(!check_reach_everything(Field::new(2565, 30), &rule, 4));
+        assert!(!check_reach_everything(Field::new(5610, 30), &rule, 2));
+        assert!(check_reach_everything(Field::new(6125, 30), &rule, 13));
+        assert!(check_reach_everything(Field::new(11755, 30), &rule, 43));
+        assert!(check_reach_everything(Field::new(13805, 30), &rule, 43));
+        assert!(!check_reach_everything(Field::new(23275, 30), &rule, 13));
+        assert!(!check_reach_everything(Field::new(390650, 30), &rule, 13));
+    }
+
+    #[test]
+    fn find_pattern_start_test() {
+        color_backtrace::install();
+
+        assert_eq!(find_pattern_start(Field::new(0b111_100_000_000, 12)), -2);
+
+        assert_eq!(
+            find_pattern_start(Field::new(0b001_101_100, 10 * 3).rotate_left(3 * 3)),
+            3
+        );
+        assert_eq!(
+            find_pattern_start(Field::new(0b001_101_100, 10 * 3).rotate_left(3 * 2)),
+            2
+        );
+        assert_eq!(
+            find_pattern_start(Field::new(0b001_101_100, 10 * 3).rotate_left(3)),
+            1
+        );
+        assert_eq!(
+            find_pattern_start(Field::new(0b001_101_100, 10 * 3).rotate_right(3)),
+            -1
+        );
+        assert_eq!(
+            find_pattern_start(Field::new(0b001_101_100, 10 * 3).rotate_right(3 * 2)),
+            -2
+        );
+        assert_eq!(
+            find_pattern_start(Field::new(0b001_101_100, 10 * 3).rotate_right(3 * 3)),
+            -3
+        );
+
+        assert_eq!(
+            find_pattern_start(Field::new(0b001_101_100, 4 * 3).rotate_left(3)),
+            1
+        );
+        assert_eq!(
+            find_pattern_start(Field::new(0b001_101_100, 4 * 3).rotate_right(3)),
+            -1
+        );
+    }
+}
@@ -0,0 +1,343 @@
+//! Бесконечное поле на периодическом фоне.
+//!
+//! Хранится только отрезок, в котором поле отличается от фона, плюс запас пустых клеток с
+//! обеих сторон. Перед каждым шагом отрезок расширяется или обрезается так, чтобы запас был не
+//! меньше `N * N` клеток: за один шаг информация уходит не дальше чем на `N * (N - 1)` клеток,
+//! поэтому отрезок можно шагать как кольцо, и края кольца друг на друга не влияют.
+//!
+//! Фон это циклическое поле [`BlockField`], которое повторяется бесконечно в обе стороны и
+//! эволюционирует вместе с полем. Координата клетки 0 фона совпадает с абсолютной координатой 0.
+
+use super::wide::WideField;
+use super::*;
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct InfiniteField<const N: usize> {
+    cells: WideField<N>,
+    /// Абсолютная координата клетки 0 в `cells`, кратна размеру фона.
+    origin: i64,
+    background: BlockField<N>,
+    time: u64,
+}
+
+impl<const N: usize> InfiniteField<N> {
+    const MARGIN: i64 = (N * N) as i64;
+
+    /// Поле, целиком совпадающее с фоном `background`.
+    pub fn new(background: BlockField<N>) -> Self {
+        Self {
+            cells: WideField::from_field(background, background.size()),
+            origin: 0,
+            background,
+            time: 0,
+        }
+    }
+
+    /// Поле на пустом фоне, в клетках `0..64` которого записано `val`.
+    pub fn from_u64(val: u64) -> Self {
+        let mut result = Self::new(BlockField::new(0, N as u32));
+        for i in 0..occupied_size(val) {
+            result.set(i as i64, (val >> i) & 1 != 0);
+        }
+        result
+    }
+
+    /// Записывает `x` в клетки начиная с координаты `position`.
+    pub fn put(&mut self, x: &WideField<N>, position: i64) {
+        for i in 0..x.size() {
+            self.set(position + i as i64, x.index(i));
+        }
+    }
+
+    pub fn background(&self) -> BlockField<N> {
+        self.background
+    }
+
+    /// Абсолютная координата первой хранимой клетки.
+    pub fn origin(&self) -> i64 {
+        self.origin
+    }
+
+    /// Количество сделанных полных шагов и фаз, в фазах.
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn period(&self) -> i64 {
+        self.background.size() as i64
+    }
+
+    fn end(&self) -> i64 {
+        self.origin + self.cells.size() as i64
+    }
+
+    fn background_at(&self, pos: i64) -> bool {
+        self.background.index(pos.rem_euclid(self.period()) as u32)
+    }
+
+    pub fn get(&self, pos: i64) -> bool {
+        if (self.origin..self.end()).contains(&pos) {
+            self.cells.index((pos - self.origin) as u32)
+        } else {
+            self.background_at(pos)
+        }
+    }
+
+    /// Отличается ли клетка от фона.
+    pub fn differs(&self, pos: i64) -> bool {
+        self.get(pos) != self.background_at(pos)
+    }
+
+    pub fn set(&mut self, pos: i64, value: bool) {
+        if !(self.origin..self.end()).contains(&pos) {
+            let from = self.origin.min(pos);
+            let to = self.end().max(pos + 1);
+            self.resize(from, to);
+        }
+        self.cells.set((pos - self.origin) as u32, value);
+    }
+
+    /// Отрезок `[from, to)` клеток, которые отличаются от фона.
+    pub fn extent(&self) -> Option<(i64, i64)> {
+        let differs = |i: &u32| {
+            let pos = self.origin + *i as i64;
+            self.cells.index(*i) != self.background_at(pos)
+        };
+        let size = self.cells.size();
+        let from = (0..size).find(differs)?;
+        let to = (0..size).rev().find(differs).unwrap() + 1;
+        Some((self.origin + from as i64, self.origin + to as i64))
+    }
+
+    /// Начало и клетки отрезка, отличающегося от фона. Начало выровнено по периоду фона,
+    /// поэтому формы двух полей с одинаковым фоном можно сравнивать напрямую.
+    pub fn shape(&self) -> (i64, Vec<bool>) {
+        match self.extent() {
+            Some((from, to)) => {
+                let from = from.div_euclid(self.period()) * self.period();
+                (from, (from..to).map(|pos| self.get(pos)).collect())
+            }
+            None => (0, Vec::new()),
+        }
+    }
+
+    // перестраивает хранимый отрезок, чтобы он покрывал хотя бы [from, to)
+    fn resize(&mut self, from: i64, to: i64) {
+        let period = self.period();
+        let from = from.div_euclid(period) * period;
+        let to = (to + period - 1).div_euclid(period) * period;
+        let mut cells = WideField::new((to - from) as u32);
+        for pos in from..to {
+            if self.get(pos) {
+                cells.set((pos - from) as u32, true);
+            }
+        }
+        self.cells = cells;
+        self.origin = from;
+    }
+
+    /// Делает запас фона с обеих сторон от `MARGIN` до `3 * MARGIN` клеток.
+    fn reserve(&mut self) {
+        let (from, to) = match self.extent() {
+            Some(extent) => extent,
+            None => {
+                if self.cells.size() as i64 > self.period() {
+                    self.resize(0, self.period());
+                }
+                return;
+            }
+        };
+        let left = from - self.origin;
+        let right = self.end() - to;
+        let range = Self::MARGIN..=3 * Self::MARGIN + self.period();
+        if !range.contains(&left) || !range.contains(&right) {
+            self.resize(from - 2 * Self::MARGIN, to + 2 * Self::MARGIN);
+        }
+    }
+
+    /// Клетки отрезка `[from, to)` в порядке [`BlockField::to_bools`], старшая клетка первая.
+    pub fn to_bools(&self, from: i64, to: i64) -> Vec<bool> {
+        (from..to).rev().map(|pos| self.get(pos)).collect()
+    }
+}
+
+impl<const N: usize> Cells<N> for InfiniteField<N> {
+    fn apply_phase(&mut self, rule: &BlockRule<N>, phase: u32) {
+        self.reserve();
+        rule.step(&mut self.cells, phase);
+        rule.step(&mut self.background, phase);
+        self.time += 1;
+    }
+
+    fn apply_steps(&mut self, rule: &BlockRule<N>) {
+        self.reserve();
+        rule.steps(&mut self.cells);
+        rule.steps(&mut self.background);
+        self.time += N as u64;
+    }
+}
+
+/// Точно находит период и смещение в блоках, не более чем за `max_steps` шагов. В отличие от
+/// [`super::gliders::exact_period`] не зависит от размера кольца.
+pub fn period<const N: usize, R: Evolution<N>>(
+    mut x: InfiniteField<N>,
+    rule: &R,
+    max_steps: u64,
+) -> Option<(u64, i32)> {
+    let background = x.background;
+    let (start, shape) = x.shape();
+    for period in 1..=max_steps {
+        rule.steps(&mut x);
+        if x.background == background {
+            let (now, now_shape) = x.shape();
+            if now_shape == shape {
+                return Some((period, ((now - start) / N as i64) as i32));
+            }
+        }
+    }
+    None
+}
+
+/// Проверяет, является ли паттерн глайдерным ружьём на бесконечном пустом поле: через
+/// `count` шагов в клетках исходного паттерна снова он сам, а всё остальное это глайдер,
+/// улетающий от ружья; ещё через `count` шагов рядом с ним появляется второй такой же глайдер.
+/// Возвращает период ружья, период и смещение глайдера.
+pub fn is_this_glider_gun<const N: usize, R: Evolution<N>>(
+    val: u64,
+    rule: &R,
+    max_steps: u64,
+) -> Option<(u64, u64, i32)> {
+    let size = occupied_size_n(val, N as u32) as i64;
+    let gun = |pos: i64| (0..size).contains(&pos) && (val >> pos) & 1 != 0;
+    let mut x = InfiniteField::<N>::from_u64(val);
+    for count in 1..=max_steps {
+        rule.steps(&mut x);
+        if x.background != BlockField::new(0, N as u32) {
+            return None;
+        }
+        let (from, to) = x.extent()?;
+        let same_gun = (0..size).all(|pos| x.get(pos) == gun(pos));
+        if !same_gun || (from >= 0 && to <= size) {
+            continue;
+        }
+
+        let mut glider = x.clone();
+        for pos in 0..size {
+            glider.set(pos, false);
+        }
+        let (glider_from, _) = glider.extent()?;
+        let (period, offset) = match period(glider.clone(), rule, max_steps) {
+            Some(found) => found,
+            None => continue,
+        };
+        let away = (glider_from < 0 && offset < 0) || (glider_from >= size && offset > 0);
+        if !away || period > count {
+            continue;
+        }
+
+        let mut later = x.clone();
+        let mut glider_later = glider.clone();
+        rule.steps_count(&mut later, count);
+        rule.steps_count(&mut glider_later, count);
+        let (later_from, later_to) = later.extent()?;
+        let (gl_from, gl_to) = glider_later.extent()?;
+        let (from, to) = (
+            later_from.min(gl_from).min(from),
+            later_to.max(gl_to).max(to),
+        );
+        if (from..to)
+            .all(|pos| later.get(pos) == (gun(pos) | glider.get(pos) | glider_later.get(pos)))
+        {
+            return Some((count, period, offset));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window3::Rule;
+
+    #[test]
+    fn grows_and_shrinks() {
+        let rule = Rule::num_to_rule(633);
+        let mut x = InfiniteField::<3>::from_u64(0b101);
+        let (start, shape) = x.shape();
+        for _ in 0..100 {
+            rule.steps(&mut x);
+            let (from, to) = x.extent().unwrap();
+            assert!(x.origin() <= from - InfiniteField::<3>::MARGIN);
+            assert!(to - from <= 9);
+            assert!((x.cells.size() as i64) < 100);
+        }
+        let (now, now_shape) = x.shape();
+        assert_eq!(now_shape, shape);
+        assert_eq!((now - start) % 3, 0);
+        assert_ne!(now, start);
+    }
+
+    #[test]
+    fn same_as_ring_while_far_from_wrap() {
+        let mut random = crate::Random::new(25025069121927896);
+        for ni in [173, 633, 876, 20000] {
+            let rule = Rule::num_to_rule(ni);
+            for background in [0, random.get() & low_mask(6), random.get() & low_mask(9)] {
+                let size = if background > low_mask(6) { 9 } else { 6 };
+                let background = BlockField::<3>::new(background, size);
+                let val = random.get() & low_mask(12);
+                let mut x = InfiniteField::new(background);
+                let mut ring = WideField::<3>::new(306 - 306 % size);
+                for pos in 0..ring.size() {
+                    ring.set(pos, background.index(pos % size));
+                }
+                for pos in 0..12 {
+                    x.set(pos as i64, (val >> pos) & 1 != 0);
+                    ring.set(pos, (val >> pos) & 1 != 0);
+                }
+                for _ in 0..10 {
+                    rule.steps(&mut x);
+                    rule.steps(&mut ring);
+                }
+                for pos in -100i64..100 {
+                    let ring_pos = pos.rem_euclid(ring.size() as i64) as u32;
+                    assert_eq!(x.get(pos), ring.index(ring_pos));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn exact_period() {
+        let rule = Rule::num_to_rule(633);
+        assert_eq!(
+            period(InfiniteField::from_u64(0b101), &rule, 100),
+            gliders::exact_period(BlockField::new(0b101, 30), &rule, None)
+        );
+        assert_eq!(
+            period(InfiniteField::from_u64(0b1010), &rule, 100),
+            gliders::exact_period(BlockField::new(0b1010, 30), &rule, None)
+        );
+        assert_eq!(
+            period(InfiniteField::from_u64(0b101101), &rule, 100),
+            gliders::exact_period(BlockField::new(0b101101, 30), &rule, None)
+        );
+    }
+
+    #[test]
+    fn glider_gun() {
+        let rule = Rule::num_to_rule(116);
+        assert_eq!(is_this_glider_gun(7, &rule, 15), None);
+
+        let rule = Rule::num_to_rule(173);
+        assert_eq!(is_this_glider_gun(59, &rule, 15), None);
+        assert_eq!(is_this_glider_gun(1250, &rule, 15), Some((5, 2, -1)));
+
+        let rule = Rule::num_to_rule(194);
+        assert_eq!(is_this_glider_gun(2, &rule, 15), Some((2, 2, -1)));
+        assert_eq!(is_this_glider_gun(9362, &rule, 15), Some((2, 2, -1)));
+
+        let rule = Rule::num_to_rule(876);
+        assert_eq!(is_this_glider_gun(11, &rule, 15), Some((1, 1, 1)));
+    }
+}
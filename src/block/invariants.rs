@@ -0,0 +1,333 @@
+//! Аддитивные сохраняющиеся величины.
+//!
+//! Величина задаётся плотностью: функцией от окна из `width` клеток и от положения начала
+//! окна внутри блока. На кольце она равна сумме плотности по всем окнам, окна циклические.
+//! Сохранение за полный шаг это линейное условие на значения плотности, поэтому все
+//! сохраняющиеся величины образуют подпространство, а базис ищется исключением Гаусса над
+//! целыми числами. Плотности, сумма которых на любом кольце зависит только от его размера,
+//! сохраняются тривиально: количество клеток, разности соседних окон и т.п. Они выкидываются,
+//! и остаётся базис факторпространства, каждый вектор которого приведён по тривиальным.
+
+use super::*;
+use std::collections::HashSet;
+use std::fmt;
+
+/// Аддитивная сохраняющаяся величина.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Invariant<const N: usize> {
+    pub width: u32,
+    /// Значение плотности для окна `w`, которое начинается в клетке `j` блока, лежит по
+    /// индексу `j << width | w`. Бит `t` окна это клетка `j + t`.
+    pub density: Vec<i64>,
+}
+
+impl<const N: usize> Invariant<N> {
+    /// Значение величины на кольце `x`.
+    pub fn value(&self, x: &BlockField<N>) -> i64 {
+        windows(x, self.width)
+            .map(|index| self.density[index])
+            .sum()
+    }
+}
+
+impl<const N: usize> fmt::Display for Invariant<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let terms = self
+            .density
+            .iter()
+            .enumerate()
+            .filter(|(_, coef)| **coef != 0)
+            .map(|(index, coef)| {
+                let window = (0..self.width)
+                    .map(|t| if (index >> t) & 1 != 0 { '1' } else { '0' })
+                    .collect::<String>();
+                format!("{}*{}:{}", coef, index >> self.width, window)
+            })
+            .collect::<Vec<_>>();
+        write!(f, "{}", terms.join(" + "))
+    }
+}
+
+// индексы плотности для всех окон кольца
+fn windows<const N: usize>(x: &BlockField<N>, width: u32) -> impl Iterator<Item = usize> + '_ {
+    let size = x.size();
+    // кольцо, повторённое столько раз, чтобы любое окно читалось одним сдвигом
+    let repeated =
+        (0..width.div_ceil(size) + 1).fold(0u128, |acc, i| acc | (x.val() as u128) << (i * size));
+    let mask = (1 << width) - 1;
+    (0..size).map(move |i| (i as usize % N) << width | (repeated >> i) as usize & mask)
+}
+
+// сколько раз встречается каждый индекс плотности
+fn counts<const N: usize>(x: &BlockField<N>, width: u32) -> Vec<u8> {
+    let mut result = vec![0; N << width];
+    for index in windows(x, width) {
+        result[index] += 1;
+    }
+    result
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// делит строку на НОД и делает первый ненулевой элемент положительным
+fn normalize(row: &mut [i128]) {
+    let divisor = row.iter().fold(0, |acc, a| gcd(acc, *a));
+    let sign = row.iter().find(|a| **a != 0).map_or(1, |a| a.signum());
+    if divisor != 0 {
+        row.iter_mut().for_each(|a| *a = *a / divisor * sign);
+    }
+}
+
+/// Ступенчатый вид набора целых строк, строки хранятся вместе с номером ведущего столбца.
+struct Echelon {
+    rows: Vec<(usize, Vec<i128>)>,
+}
+
+impl Echelon {
+    fn new() -> Self {
+        Self { rows: Vec::new() }
+    }
+
+    // зануляет в `row` ведущие столбцы всех строк
+    fn reduce(&self, mut row: Vec<i128>) -> Vec<i128> {
+        for (pivot, other) in &self.rows {
+            let a = row[*pivot];
+            if a != 0 {
+                let b = other[*pivot];
+                for (x, y) in row.iter_mut().zip(other) {
+                    *x = *x * b - y * a;
+                }
+                if row.iter().any(|x| x.abs() > 1 << 60) {
+                    normalize(&mut row);
+                }
+            }
+        }
+        normalize(&mut row);
+        row
+    }
+
+    // добавляет строку, если она не выражается через уже добавленные
+    fn insert(&mut self, row: Vec<i128>) {
+        let mut row = self.reduce(row);
+        if let Some(pivot) = row.iter().position(|a| *a != 0) {
+            normalize(&mut row);
+            for (_, other) in &mut self.rows {
+                let a = other[pivot];
+                if a != 0 {
+                    let b = row[pivot];
+                    for (x, y) in other.iter_mut().zip(&row) {
+                        *x = *x * b - y * a;
+                    }
+                    normalize(other);
+                }
+            }
+            self.rows.push((pivot, row));
+        }
+    }
+
+    fn rank(&self) -> usize {
+        self.rows.len()
+    }
+
+    // базис пространства решений `row * v = 0` для всех строк
+    fn nullspace(&self, columns: usize) -> Vec<Vec<i128>> {
+        let pivots = self
+            .rows
+            .iter()
+            .map(|(pivot, _)| *pivot)
+            .collect::<HashSet<_>>();
+        (0..columns)
+            .filter(|free| !pivots.contains(free))
+            .map(|free| {
+                let scale = self.rows.iter().fold(1, |acc, (pivot, row)| {
+                    let d = row[*pivot] / gcd(row[*pivot], row[free]);
+                    acc / gcd(acc, d) * d
+                });
+                let mut v = vec![0; columns];
+                v[free] = scale;
+                for (pivot, row) in &self.rows {
+                    v[*pivot] = -row[free] * scale / row[*pivot];
+                }
+                normalize(&mut v);
+                v
+            })
+            .collect()
+    }
+}
+
+/// Размер колец, на которых проверяется сохранение: окно плотности вместе с тем, куда за
+/// полный шаг доходит влияние с обеих сторон, плюс ещё один блок.
+pub fn check_size<const N: usize, R: Evolution<N>>(rule: &R, width: u32) -> u32 {
+    let n = N as u32;
+    let spread = rule.phases() * (n - 1);
+    (width + 2 * spread + n).div_ceil(n) * n
+}
+
+// все кольца размера от `N` до `max_size`
+fn rings<const N: usize>(max_size: u32) -> impl Iterator<Item = BlockField<N>> {
+    (N as u32..=max_size)
+        .step_by(N)
+        .flat_map(|size| (0..=low_mask(size)).map(move |val| BlockField::<N>::new(val, size)))
+}
+
+fn difference(a: &[u8], b: &[u8]) -> Vec<i128> {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| *a as i128 - *b as i128)
+        .collect()
+}
+
+/// Тривиальные плотности ширины `width`: их сумма на любом кольце размера до `max_size`
+/// зависит только от размера. От правила они не зависят, поэтому при переборе правил
+/// считаются один раз через [`trivial_quantities`] и передаются в
+/// [`conserved_quantities_with`].
+pub struct Trivial<const N: usize> {
+    width: u32,
+    max_size: u32,
+    basis: Echelon,
+}
+
+/// Базис тривиальных плотностей ширины `width` на кольцах размера до `max_size`.
+pub fn trivial_quantities<const N: usize>(width: u32, max_size: u32) -> Trivial<N> {
+    assert!(
+        max_size <= 30 && width <= 16,
+        "rings are too large to enumerate"
+    );
+
+    // строки `counts(x) - counts(0)`, решения дают величины, зависящие только от размера
+    let mut trivial = Echelon::new();
+    let mut seen = HashSet::new();
+    for x in rings::<N>(max_size) {
+        let before = counts(&x, width);
+        if seen.insert(before.clone()) {
+            let zero = counts(&BlockField::<N>::new(0, x.size()), width);
+            trivial.insert(difference(&before, &zero));
+        }
+    }
+
+    let mut basis = Echelon::new();
+    for v in trivial.nullspace(N << width) {
+        basis.insert(v);
+    }
+    Trivial {
+        width,
+        max_size,
+        basis,
+    }
+}
+
+/// Базис нетривиальных аддитивных сохраняющихся величин с плотностью ширины `width`.
+/// Величины меньшей ширины тоже сюда входят, как плотности, которые не зависят от последних
+/// клеток окна. Сохранение проверяется за полный шаг на всех кольцах, в которые помещается
+/// окно вместе с областью влияния, поэтому ни одна настоящая величина не теряется.
+pub fn conserved_quantities<const N: usize, R: Evolution<N>>(
+    rule: &R,
+    width: u32,
+) -> Vec<Invariant<N>> {
+    conserved_quantities_with(rule, &trivial_quantities(width, check_size(rule, width)))
+}
+
+/// То же, что [`conserved_quantities`], но с заранее посчитанными тривиальными плотностями.
+/// Они должны быть посчитаны на кольцах размера [`check_size`] для этого правила.
+pub fn conserved_quantities_with<const N: usize, R: Evolution<N>>(
+    rule: &R,
+    trivial: &Trivial<N>,
+) -> Vec<Invariant<N>> {
+    let width = trivial.width;
+    let columns = N << width;
+    assert_eq!(trivial.max_size, check_size(rule, width));
+
+    // строки `counts(T x) - counts(x)`; тривиальные величины всегда сохраняются, поэтому
+    // когда ранги совпали, других величин уже нет
+    let trivial_rank = columns - trivial.basis.rank();
+    let mut conserved = Echelon::new();
+    let mut seen = HashSet::new();
+    for x in rings::<N>(trivial.max_size) {
+        if conserved.rank() == trivial_rank {
+            break;
+        }
+        let mut y = x;
+        rule.steps(&mut y);
+        let (before, after) = (counts(&x, width), counts(&y, width));
+        if before != after && seen.insert((before.clone(), after.clone())) {
+            conserved.insert(difference(&after, &before));
+        }
+    }
+
+    let mut result = Echelon::new();
+    for v in conserved.nullspace(columns) {
+        result.insert(trivial.basis.reduce(v));
+    }
+    result.rows.sort();
+    result
+        .rows
+        .into_iter()
+        .map(|(_, row)| Invariant {
+            width,
+            density: row.into_iter().map(|a| a as i64).collect(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window3::Rule;
+    use crate::Random;
+
+    fn check(rule: &Rule, invariants: &[Invariant<3>]) {
+        let mut random = Random::new(25025069121927896);
+        for _ in 0..100 {
+            let mut x = BlockField::<3>::new(random.get() & low_mask(60), 60);
+            let values = invariants.iter().map(|i| i.value(&x)).collect::<Vec<_>>();
+            rule.steps_count(&mut x, 5);
+            assert_eq!(
+                values,
+                invariants.iter().map(|i| i.value(&x)).collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn identity() {
+        // количество единиц в каждой клетке блока
+        let invariants = conserved_quantities(&Rule::RULE0, 1);
+        assert_eq!(invariants.len(), 3);
+        check(&Rule::RULE0, &invariants);
+        let invariants = conserved_quantities(&Rule::RULE0, 2);
+        assert!(invariants.len() > 3);
+        check(&Rule::RULE0, &invariants);
+    }
+
+    #[test]
+    fn particles() {
+        // перестановка внутри блоков сохраняет количество единиц
+        let mut p = (0..8).collect::<Vec<_>>();
+        p[1] = 2;
+        p[2] = 4;
+        p[4] = 1;
+        let rule = Rule::from_permutation(&p);
+        let invariants = conserved_quantities(&rule, 1);
+        check(&rule, &invariants);
+        let ones = Invariant::<3> {
+            width: 1,
+            density: vec![0, 1, 0, 1, 0, 1],
+        };
+        assert!(invariants.contains(&ones));
+        assert_eq!(format!("{}", ones), "1*0:1 + 1*1:1 + 1*2:1");
+
+        let trivial = trivial_quantities(2, check_size(&Rule::RULE0, 2));
+        for ni in [173, 633, 876] {
+            let rule = Rule::num_to_rule(ni);
+            let invariants = conserved_quantities_with(&rule, &trivial);
+            assert_eq!(invariants, conserved_quantities(&rule, 2));
+            check(&rule, &invariants);
+        }
+    }
+}
@@ -0,0 +1,288 @@
+//! Отслеживание частиц в пространственно-временной диаграмме.
+//!
+//! После каждого полного шага поле [`InfiniteField`] делится на куски, отличающиеся от фона и
+//! разделённые длинными промежутками фона, как в [`super::collisions`]. Куски соседних шагов
+//! связываются, если за шаг влияние могло дойти от одного до другого. Связанные группы из
+//! одного куска до и одного после продолжают траекторию, а все остальные группы это
+//! взаимодействия: траектории, которые в них вошли, заканчиваются, и из них начинаются новые.
+//! Каждая траектория опознаётся по каталогу глайдеров, как продукты столкновений.
+
+use super::collisions::{self, Species};
+use super::infinite::{self, InfiniteField};
+use super::*;
+use std::collections::BTreeMap;
+
+/// Траектория одного куска.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Trajectory {
+    /// Первый и последний полные шаги, на которых кусок был отдельным.
+    pub start: u64,
+    pub end: u64,
+    /// Отрезок `[from, to)`, отличающийся от фона, на каждом шаге от `start` до `end`.
+    pub path: Vec<(i64, i64)>,
+    /// Период и смещение в блоках отдельно взятого куска на шаге `end`, если он периодический.
+    pub motion: Option<(u64, i32)>,
+    /// Номер в каталоге, если кусок это глайдер оттуда.
+    pub species: Option<usize>,
+}
+
+impl Trajectory {
+    /// Смещение в блоках за период, 0 для неопознанных кусков.
+    pub fn offset(&self) -> i32 {
+        self.motion.map_or(0, |(_, offset)| offset)
+    }
+
+    /// Средняя скорость середины отрезка в клетках за полный шаг.
+    pub fn velocity(&self) -> f64 {
+        let center = |(from, to): (i64, i64)| (from + to) as f64 / 2.0;
+        match (self.path.first(), self.path.last()) {
+            (Some(first), Some(last)) if self.end > self.start => {
+                (center(*last) - center(*first)) / (self.end - self.start) as f64
+            }
+            _ => 0.0,
+        }
+    }
+}
+
+/// Место, где траектории сходятся, расходятся, появляются или исчезают.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Interaction {
+    /// Полный шаг, на котором появились выходящие траектории.
+    pub time: u64,
+    /// Отрезок, который покрывают все участвующие куски.
+    pub from: i64,
+    pub to: i64,
+    /// Номера траекторий, которые закончились на шаге `time - 1` и начались на шаге `time`.
+    pub incoming: Vec<usize>,
+    pub outgoing: Vec<usize>,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Tracking {
+    pub trajectories: Vec<Trajectory>,
+    pub interactions: Vec<Interaction>,
+}
+
+// активная траектория вместе с последним снимком куска
+struct Active<const N: usize> {
+    index: usize,
+    part: InfiniteField<N>,
+}
+
+fn extent<const N: usize>(part: &InfiniteField<N>) -> (i64, i64) {
+    part.extent().unwrap()
+}
+
+// корень множества в системе непересекающихся множеств
+fn find(parent: &mut [usize], i: usize) -> usize {
+    if parent[i] != i {
+        parent[i] = find(parent, parent[i]);
+    }
+    parent[i]
+}
+
+fn start<const N: usize>(result: &mut Tracking, part: InfiniteField<N>, time: u64) -> Active<N> {
+    result.trajectories.push(Trajectory {
+        start: time,
+        end: time,
+        path: vec![extent(&part)],
+        motion: None,
+        species: None,
+    });
+    Active {
+        index: result.trajectories.len() - 1,
+        part,
+    }
+}
+
+/// Следит за кусками поля `x` в течение `steps` полных шагов. Куски опознаются по каталогу
+/// `catalogue`, заданному на пустом фоне, период куска ищется не дольше `max_period` шагов.
+pub fn track<const N: usize, R: Evolution<N>>(
+    rule: &R,
+    mut x: InfiniteField<N>,
+    steps: u64,
+    catalogue: &[Species],
+    max_period: u64,
+) -> Tracking {
+    let reach = (rule.phases() * (N as u32 - 1)) as i64;
+    let mut result = Tracking::default();
+    let mut active = collisions::split(&x)
+        .into_iter()
+        .map(|part| start(&mut result, part, 0))
+        .collect::<Vec<_>>();
+    let mut finished = Vec::new();
+
+    for time in 1..=steps {
+        rule.steps(&mut x);
+        let parts = collisions::split(&x);
+
+        // номера `0..before` это куски до шага, дальше куски после
+        let before = active.len();
+        let mut parent = (0..before + parts.len()).collect::<Vec<_>>();
+        for (i, current) in active.iter().enumerate() {
+            let (from, to) = extent(&current.part);
+            for (j, part) in parts.iter().enumerate() {
+                let (a, b) = extent(part);
+                if a < to + reach && from - reach < b {
+                    let (i, j) = (find(&mut parent, i), find(&mut parent, before + j));
+                    parent[i] = j;
+                }
+            }
+        }
+        let mut groups = BTreeMap::new();
+        for k in 0..parent.len() {
+            let root = find(&mut parent, k);
+            groups.entry(root).or_insert_with(Vec::new).push(k);
+        }
+
+        let mut previous = std::mem::take(&mut active)
+            .into_iter()
+            .map(Some)
+            .collect::<Vec<_>>();
+        let mut parts = parts.into_iter().map(Some).collect::<Vec<_>>();
+        for members in groups.values() {
+            let (incoming, outgoing): (Vec<usize>, Vec<usize>) =
+                members.iter().partition(|k| **k < before);
+            if let ([i], [j]) = (incoming.as_slice(), outgoing.as_slice()) {
+                let mut current = previous[*i].take().unwrap();
+                current.part = parts[*j - before].take().unwrap();
+                let trajectory = &mut result.trajectories[current.index];
+                trajectory.end = time;
+                trajectory.path.push(extent(&current.part));
+                active.push(current);
+                continue;
+            }
+
+            let mut interaction = Interaction {
+                time,
+                from: i64::MAX,
+                to: i64::MIN,
+                incoming: Vec::new(),
+                outgoing: Vec::new(),
+            };
+            for i in incoming {
+                let current = previous[i].take().unwrap();
+                let (from, to) = extent(&current.part);
+                interaction.from = interaction.from.min(from);
+                interaction.to = interaction.to.max(to);
+                interaction.incoming.push(current.index);
+                finished.push(current);
+            }
+            for j in outgoing {
+                let part = parts[j - before].take().unwrap();
+                let (from, to) = extent(&part);
+                interaction.from = interaction.from.min(from);
+                interaction.to = interaction.to.max(to);
+                let current = start(&mut result, part, time);
+                interaction.outgoing.push(current.index);
+                active.push(current);
+            }
+            result.interactions.push(interaction);
+        }
+        active.sort_by_key(|current| current.index);
+    }
+
+    let shapes = collisions::shapes(rule, catalogue);
+    for current in finished.into_iter().chain(active) {
+        let trajectory = &mut result.trajectories[current.index];
+        trajectory.motion = infinite::period(current.part.clone(), rule, max_period);
+        if let Some((period, _)) = trajectory.motion {
+            let shape = collisions::canonical(rule, current.part, period);
+            trajectory.species = shapes.iter().position(|known| *known == shape);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window3::Rule;
+    use crate::Random;
+
+    fn check_consistency(tracking: &Tracking, steps: u64) {
+        for (index, trajectory) in tracking.trajectories.iter().enumerate() {
+            assert_eq!(
+                trajectory.path.len() as u64,
+                trajectory.end - trajectory.start + 1
+            );
+            let ends = tracking
+                .interactions
+                .iter()
+                .filter(|i| i.incoming.contains(&index))
+                .collect::<Vec<_>>();
+            let starts = tracking
+                .interactions
+                .iter()
+                .filter(|i| i.outgoing.contains(&index))
+                .collect::<Vec<_>>();
+            assert_eq!(ends.len() as u64, (trajectory.end < steps) as u64);
+            assert_eq!(starts.len() as u64, (trajectory.start > 0) as u64);
+            assert!(ends.iter().all(|i| i.time == trajectory.end + 1));
+            assert!(starts.iter().all(|i| i.time == trajectory.start));
+        }
+    }
+
+    #[test]
+    fn pass_through() {
+        let rule = Rule::num_to_rule(633);
+        let catalogue = gliders::get_gliders(&rule, 64, Some(200), false)
+            .into_iter()
+            .collect::<Vec<_>>();
+        let index = |species: Species| catalogue.iter().position(|s| *s == species).unwrap();
+        let (left, right) = (index((0b101, 4, 1)), index((0b1010, 2, -1)));
+
+        let mut x = InfiniteField::<3>::from_u64(0b101);
+        x.set(120 + 1, true);
+        x.set(120 + 3, true);
+        let tracking = track(&rule, x, 150, &catalogue, 100);
+        check_consistency(&tracking, 150);
+
+        let trajectories = &tracking.trajectories;
+        assert_eq!(trajectories[0].species, Some(left));
+        assert_eq!(trajectories[1].species, Some(right));
+        assert!((trajectories[0].velocity() - 0.75).abs() < 0.1);
+        assert!((trajectories[1].velocity() + 1.5).abs() < 0.1);
+        assert_eq!(tracking.interactions[0].incoming, vec![0, 1]);
+
+        // в конце те же два глайдера разлетаются
+        let last = tracking
+            .trajectories
+            .iter()
+            .filter(|t| t.end == 150)
+            .collect::<Vec<_>>();
+        assert_eq!(last.len(), 2);
+        let mut species = last.iter().map(|t| t.species).collect::<Vec<_>>();
+        species.sort();
+        assert_eq!(species, vec![Some(left.min(right)), Some(left.max(right))]);
+    }
+
+    #[test]
+    fn random_field() {
+        let mut random = Random::new(25025069121927896);
+        for ni in [173, 633, 876] {
+            let rule = Rule::num_to_rule(ni);
+            let catalogue = gliders::get_gliders(&rule, 64, Some(200), false)
+                .into_iter()
+                .collect::<Vec<_>>();
+            let mut x = InfiniteField::<3>::from_u64(0);
+            for pos in 0..90 {
+                x.set(pos, random.get().is_multiple_of(4));
+            }
+            let tracking = track(&rule, x, 200, &catalogue, 100);
+            check_consistency(&tracking, 200);
+            if ni == 633 {
+                // из случайного поля вылетают глайдеры из каталога
+                assert!(tracking
+                    .trajectories
+                    .iter()
+                    .any(|t| t.end == 200 && t.offset() != 0 && t.species.is_some()));
+            }
+            for trajectory in &tracking.trajectories {
+                if let (Some(species), Some(motion)) = (trajectory.species, trajectory.motion) {
+                    assert_eq!((catalogue[species].1, catalogue[species].2), motion);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Расписание фаз вместо фиксированных смещений `0, 1, ..., N - 1`.
+//!
+//! Полный шаг это список фаз, на каждой своё смещение блоков и, возможно, своё правило.
+//! Например смещения `0, 2, 1`, только смещения `0, 1` при окне 3 или правило A на чётных
+//! фазах и правило B на нечётных. Расписание реализует [`Evolution`], поэтому его принимают
+//! [`super::gliders`], [`super::infinite`] и [`Evolution::show_field`].
+
+use super::*;
+
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Schedule<const N: usize> {
+    // (смещение блоков, правило)
+    phases: Vec<(u32, BlockRule<N>)>,
+}
+
+impl<const N: usize> Schedule<N> {
+    pub fn new(phases: Vec<(u32, BlockRule<N>)>) -> Self {
+        assert!(!phases.is_empty());
+        assert!(phases.iter().all(|(offset, _)| *offset < N as u32));
+        Self { phases }
+    }
+
+    /// Одно правило с заданными смещениями фаз.
+    pub fn with_offsets(rule: &BlockRule<N>, offsets: &[u32]) -> Self {
+        Self::new(offsets.iter().map(|offset| (*offset, *rule)).collect())
+    }
+
+    /// Смещения `0, 1, ..., N - 1`, правило `a` на чётных фазах и `b` на нечётных. При нечётном
+    /// `N` полный шаг состоит из `2 * N` фаз, чтобы чётность фазы не зависела от номера шага.
+    pub fn alternating(a: &BlockRule<N>, b: &BlockRule<N>) -> Self {
+        let count = if N.is_multiple_of(2) { N } else { 2 * N };
+        Self::new(
+            (0..count)
+                .map(|i| ((i % N) as u32, if i % 2 == 0 { *a } else { *b }))
+                .collect(),
+        )
+    }
+
+    pub fn phases_list(&self) -> &[(u32, BlockRule<N>)] {
+        &self.phases
+    }
+
+    /// Обратное расписание: фазы в обратном порядке с обратными правилами.
+    pub fn inverse(&self) -> Self {
+        Self::new(
+            self.phases
+                .iter()
+                .rev()
+                .map(|(offset, rule)| (*offset, rule.inverse()))
+                .collect(),
+        )
+    }
+}
+
+impl<const N: usize> From<BlockRule<N>> for Schedule<N> {
+    fn from(rule: BlockRule<N>) -> Self {
+        Self::with_offsets(&rule, &(0..N as u32).collect::<Vec<_>>())
+    }
+}
+
+impl<const N: usize> Evolution<N> for Schedule<N> {
+    fn phases(&self) -> u32 {
+        self.phases.len() as u32
+    }
+
+    fn offset(&self, phase: u32) -> u32 {
+        self.phases[(phase % self.phases()) as usize].0
+    }
+
+    fn phase<F: Cells<N>>(&self, x: &mut F, phase: u32) {
+        let (offset, rule) = &self.phases[(phase % self.phases()) as usize];
+        rule.step(x, *offset);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window3::{Field, Rule};
+    use crate::Random;
+
+    #[test]
+    fn same_as_rule() {
+        let mut random = Random::new(25025069121927896);
+        for ni in [173, 633, 876] {
+            let rule = Rule::num_to_rule(ni);
+            let schedule = Schedule::from(rule);
+            let mut x = Field::new(random.get() & low_mask(63), 63);
+            let mut y = x;
+            rule.steps_count(&mut x, 20);
+            schedule.steps_count(&mut y, 20);
+            assert_eq!(x, y);
+            assert_eq!(
+                gliders::exact_period(Field::new(0b101, 30), &rule, None),
+                gliders::exact_period(Field::new(0b101, 30), &schedule, None)
+            );
+        }
+    }
+
+    #[test]
+    fn inverse() {
+        let mut random = Random::new(25025069121927896);
+        let a = Rule::num_to_rule(633);
+        let b = Rule::num_to_rule(20000);
+        for schedule in [
+            Schedule::with_offsets(&a, &[0, 2, 1]),
+            Schedule::with_offsets(&a, &[0, 1]),
+            Schedule::alternating(&a, &b),
+        ] {
+            let start = Field::new(random.get() & low_mask(63), 63);
+            let mut x = start;
+            schedule.steps_count(&mut x, 10);
+            schedule.inverse().steps_count(&mut x, 10);
+            assert_eq!(x, start);
+        }
+        assert_eq!(Schedule::alternating(&a, &b).phases(), 6);
+    }
+
+    #[test]
+    fn custom_offsets() {
+        let rule = Rule::num_to_rule(633);
+        let schedule = Schedule::with_offsets(&rule, &[0, 1]);
+        let mut x = Field::new(0b101, 30);
+        let mut y = x;
+        schedule.steps(&mut x);
+        rule.step(&mut y, 0);
+        rule.step(&mut y, 1);
+        assert_eq!(x, y);
+    }
+}
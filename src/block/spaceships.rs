@@ -0,0 +1,307 @@
+//! Поиск глайдеров заданного периода и смещения перебором с возвратом, как в gfind.
+//!
+//! Начальное состояние строится по одной клетке слева направо, и вместе с ним строится
+//! пространственно-временная диаграмма всех фаз за `period` шагов. Левее клетки 0 лежит то,
+//! во что за эти фазы переходит пустое поле, поэтому если известны клетки `[0, k)`, то на
+//! каждой фазе известен префикс строки: блок известен, если известны все его клетки, и
+//! граница префикса отступает влево до начала блока. Новая клетка добавляет на каждой фазе не
+//! больше одного известного блока, и считаются только такие блоки. Новые клетки последней
+//! строки сравниваются с начальным состоянием, сдвинутым на `offset` блоков, и ветка
+//! отсекается при первом расхождении. Паттерн найден, когда за его последней единицей столько
+//! нулей, что дальше обе строки заведомо пустые. Единицы после такого промежутка за период не
+//! встречаются с паттерном, поэтому дальше ветка не продолжается. Поиск полный: находятся все
+//! глайдеры ширины не больше `width`, у которых самая левая клетка лежит в первом блоке.
+
+use super::collisions;
+use super::infinite::InfiniteField;
+use super::*;
+use std::collections::BTreeMap;
+
+/// Найденный глайдер.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Spaceship {
+    /// Минимальная фаза, как у [`gliders::minimize2`].
+    pub pattern: u64,
+    pub period: u64,
+    /// Смещение в блоках за `period` шагов, как у [`gliders::exact_period`].
+    pub offset: i32,
+    /// Все фазы глайдера по порядку, начиная с `pattern`, каждая сдвинута так, чтобы самая
+    /// левая клетка лежала в первом блоке.
+    pub phases: Vec<u64>,
+}
+
+struct Search<'a, const N: usize, R: Evolution<N>> {
+    rule: &'a R,
+    period: u64,
+    offset: i32,
+    width: u32,
+    // столько нулей после последней единицы хватает, чтобы проверить паттерн целиком
+    tail: u32,
+    // клетка 0 начального состояния лежит в элементе `origin` каждой строки
+    origin: i64,
+    // tables[phase][block]: блок после фазы `phase`, клетки с начала блока
+    tables: Vec<[u8; MAX_STATES]>,
+    // rows[t]: поле после `t` фаз
+    rows: Vec<Vec<bool>>,
+    // frontiers[k][t]: граница известного префикса строки `t`, если известны клетки `[0, k)`
+    frontiers: Vec<Vec<i64>>,
+    found: BTreeMap<u64, Spaceship>,
+}
+
+impl<const N: usize, R: Evolution<N>> Search<'_, N, R> {
+    // клетка `pos` строки `t` после целого числа шагов, если известны клетки `[0, k)`; за
+    // границей префикса поле пустое только у законченного паттерна
+    fn get(&self, t: usize, pos: i64, k: u32) -> bool {
+        pos >= -self.origin
+            && pos < self.frontiers[k as usize][t]
+            && self.rows[t][(pos + self.origin) as usize]
+    }
+
+    // ставит клетку `k` начального состояния и досчитывает новые блоки, `false`, если новые
+    // клетки последней строки разошлись с начальным состоянием
+    fn extend(&mut self, k: u32, value: bool) -> bool {
+        let n = N as i64;
+        let (k, origin) = (k as usize, self.origin);
+        self.rows[0][(k as i64 + origin) as usize] = value;
+        let last = self.rows.len() - 1;
+        for t in 0..last {
+            let table = &self.tables[t % self.tables.len()];
+            for start in (self.frontiers[k][t + 1]..self.frontiers[k + 1][t + 1]).step_by(N) {
+                let cells = &self.rows[t][(start + origin) as usize..][..N];
+                let block = (0..N).fold(0, |acc, i| acc | (cells[i] as usize) << i);
+                let to = table[block];
+                let cells = &mut self.rows[t + 1][(start + origin) as usize..][..N];
+                for (i, cell) in cells.iter_mut().enumerate() {
+                    *cell = (to >> i) & 1 != 0;
+                }
+            }
+        }
+
+        let (from, to) = (self.frontiers[k][last], self.frontiers[k + 1][last]);
+        let shift = self.offset as i64 * n;
+        let k = k as i64;
+        // новые клетки последней строки и клетка, сдвинутая из только что поставленной
+        (from..to)
+            .chain(Some(k + shift).filter(|pos| *pos < from))
+            .all(|pos| {
+                let before = pos - shift;
+                before > k
+                    || self.rows[last][(pos + origin) as usize]
+                        == (before >= 0 && self.rows[0][(before + origin) as usize])
+            })
+    }
+
+    // строка `t` совпадает с начальным состоянием, сдвинутым на `shift` клеток
+    fn is_shifted(&self, t: usize, shift: i64, k: u32) -> bool {
+        let to = self.rows[0].len() as i64 - self.origin;
+        (-self.origin..to).all(|pos| self.get(t, pos, k) == self.get(0, pos - shift, k))
+    }
+
+    // клетки строки `t`, начиная с блока, в котором лежит самая левая клетка
+    fn normalize(&self, t: usize, k: u32) -> u64 {
+        let to = self.rows[0].len() as i64 - self.origin;
+        let cells = (-self.origin..to)
+            .filter(|pos| self.get(t, *pos, k))
+            .collect::<Vec<_>>();
+        let from = cells[0].div_euclid(N as i64) * N as i64;
+        let last = *cells.last().unwrap();
+        assert!(last - from < 64, "phase is wider than 64 cells");
+        cells.iter().fold(0, |acc, pos| acc | 1 << (pos - from))
+    }
+
+    // клетки `[0, k)` выставлены, за последней единицей `tail` нулей
+    fn record(&mut self, k: u32) {
+        let phases = self.rule.phases() as usize;
+        let smaller = (1..self.period).any(|period| {
+            let offset = self.offset as i64 * period as i64;
+            self.period.is_multiple_of(period)
+                && offset % self.period as i64 == 0
+                && self.is_shifted(
+                    period as usize * phases,
+                    offset / self.period as i64 * N as i64,
+                    k,
+                )
+        });
+        if smaller {
+            return;
+        }
+
+        let mut phases = (0..self.period as usize)
+            .map(|step| self.normalize(step * phases, k))
+            .collect::<Vec<_>>();
+        // куски, которые ни в одной фазе не сближаются, это отдельные глайдеры
+        if phases
+            .iter()
+            .all(|x| collisions::split(&InfiniteField::<N>::from_u64(*x)).len() > 1)
+        {
+            return;
+        }
+        let start = (0..phases.len()).min_by_key(|i| phases[*i]).unwrap();
+        phases.rotate_left(start);
+        self.found.entry(phases[0]).or_insert(Spaceship {
+            pattern: phases[0],
+            period: self.period,
+            offset: self.offset,
+            phases,
+        });
+    }
+
+    // клетки `[0, k)` выставлены, последняя единица в клетке `end - 1`, 0 если единиц нет
+    fn search(&mut self, k: u32, end: u32) {
+        if end > 0 && k == end + self.tail {
+            self.record(k);
+            return;
+        }
+        if end == 0 && k == N as u32 {
+            return;
+        }
+        if self.extend(k, false) {
+            self.search(k + 1, end);
+        }
+        if k < self.width && self.extend(k, true) {
+            self.search(k + 1, k + 1);
+        }
+    }
+}
+
+/// Находит все глайдеры и осцилляторы на пустом поле с периодом ровно `period` и смещением
+/// `offset` блоков, ширина начального состояния которых не больше `width` клеток. Каждый
+/// глайдер находится во всех своих фазах, но возвращается один раз. Паттерны, которые во всех
+/// фазах распадаются на отдельные куски, как в [`collisions::products`], это поезда из
+/// нескольких глайдеров, и они пропускаются.
+pub fn find_spaceships<const N: usize, R: Evolution<N>>(
+    rule: &R,
+    period: u64,
+    offset: i32,
+    width: u32,
+) -> Vec<Spaceship> {
+    assert!(period > 0 && width <= 64);
+    let mut empty = BlockField::<N>::new(0, N as u32);
+    rule.steps(&mut empty);
+    if empty.val() != 0 {
+        return Vec::new();
+    }
+
+    let n = N as i64;
+    let phases = rule.phases();
+    let steps = (period * phases as u64) as usize;
+    let spread = steps as u32 * (N as u32 - 1);
+    let tail = 2 * spread + offset.unsigned_abs() * N as u32;
+    let max_known = width.max(N as u32) + tail;
+    let origin = spread.div_ceil(N as u32) as i64 * n + n;
+
+    let tables = (0..phases)
+        .map(|phase| {
+            let o = rule.offset(phase) as usize % N;
+            let mut table = [0; MAX_STATES];
+            for (block, to) in table.iter_mut().enumerate().take(1 << N) {
+                // блок фазы начинается в клетке `o` кольца из одного блока
+                let val = (0..N).fold(0, |acc, i| acc | ((block >> i) & 1) << ((i + o) % N));
+                let mut x = BlockField::<N>::new(val as u64, N as u32);
+                rule.phase(&mut x, phase);
+                *to = (0..N).fold(0, |acc, i| acc | (x.index(((i + o) % N) as u32) as u8) << i);
+            }
+            table
+        })
+        .collect();
+    let frontiers = (0..=max_known as i64)
+        .map(|k| {
+            let mut frontier = vec![k];
+            for t in 0..steps {
+                let known = frontier[t];
+                let offset = rule.offset(t as u32 % phases) as i64;
+                frontier.push(known - (known - offset).rem_euclid(n));
+            }
+            frontier
+        })
+        .collect();
+
+    // пустое поле пусто после полного шага, но не обязательно после каждой фазы
+    let mut background = BlockField::<N>::new(0, N as u32);
+    let rows = (0..=steps)
+        .map(|t| {
+            if t > 0 {
+                rule.phase(&mut background, (t - 1) as u32 % phases);
+            }
+            (-origin..max_known as i64)
+                .map(|pos| background.index(pos.rem_euclid(n) as u32))
+                .collect()
+        })
+        .collect();
+    let mut search = Search {
+        rule,
+        period,
+        offset,
+        width,
+        tail,
+        origin,
+        tables,
+        rows,
+        frontiers,
+        found: BTreeMap::new(),
+    };
+    search.search(0, 0);
+    search.found.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window3::Rule;
+
+    #[test]
+    fn same_as_brute_force() {
+        for ni in [173, 633, 876] {
+            let rule = Rule::num_to_rule(ni);
+            let brute = gliders::get_gliders(&rule, 1 << 9, Some(200), false);
+            assert!(brute
+                .iter()
+                .any(|(_, period, offset)| *period <= 4 && *offset != 0));
+            for (val, period, offset) in brute.iter().filter(|(_, period, _)| *period <= 4) {
+                let found = find_spaceships(&rule, *period, *offset, 9);
+                let ship = found.iter().find(|ship| ship.pattern == *val).unwrap();
+                assert_eq!(ship.phases.len() as u64, *period);
+            }
+            for period in 1..=4 {
+                for offset in -2..=2 {
+                    for ship in find_spaceships(&rule, period, offset, 9) {
+                        for phase in &ship.phases {
+                            let x = BlockField::<3>::new(*phase, 45).centralize();
+                            assert!(gliders::is_same_after(x, &rule, period, offset));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn no_trains() {
+        // пустой блок здесь непуст между фазами, а одиночная клетка летит на блок за шаг
+        let rule = BlockRule::<2>::num_to_rule(12);
+        let found = find_spaceships(&rule, 1, 1, 14);
+        let patterns = found.iter().map(|ship| ship.pattern).collect::<Vec<_>>();
+        assert!(patterns.contains(&0b1));
+        assert!(patterns.contains(&0b101));
+        // две далёкие клетки это два отдельных глайдера
+        assert!(!patterns.contains(&(0b1 | 1 << 10)));
+        for ship in &found {
+            assert!(ship
+                .phases
+                .iter()
+                .any(|x| collisions::split(&InfiniteField::<2>::from_u64(*x)).len() == 1));
+        }
+    }
+
+    #[test]
+    fn long_period() {
+        // глайдер периода 13 из collide_gliders_633
+        let rule = Rule::num_to_rule(633);
+        let x = 0b1011111101;
+        let (period, offset) =
+            gliders::exact_period(BlockField::<3>::new(x, 63).centralize(), &rule, None).unwrap();
+        assert_eq!(period, 13);
+        let found = find_spaceships(&rule, period, offset, 10);
+        assert!(found.iter().any(|ship| ship.phases.contains(&x)));
+    }
+}
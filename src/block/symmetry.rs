@@ -0,0 +1,268 @@
+//! Симметрии правил: обращение времени, инверсия цвета и зеркало.
+//!
+//! В отличие от окна 2 в `rule_generations`, при `N > 2` зеркало и обращение времени меняют
+//! порядок фаз. Зеркальное отражение поля `i -> size - 1 - i` переводит блоки со смещением `o`
+//! в блоки со смещением `-o`, поэтому отражённое поле эволюционирует по правилу
+//! [`BlockRule::mirror`] с фазами `0, N - 1, ..., 1`. Шаг назад это фазы `N - 1, ..., 0` с
+//! правилом [`BlockRule::invert_time`]. Их композиция снова даёт возрастающий порядок фаз
+//! `1, 2, ..., N - 1, 0`, то есть обычный шаг поля, сдвинутого на одну клетку: поэтому
+//! [`BlockRule::mirror_time`] это точная симметрия в классе обычных правил, а зеркало и
+//! обращение времени по отдельности нет. Инверсия цвета порядок фаз не меняет.
+
+use super::schedule::Schedule;
+use super::*;
+
+// разворачивает порядок клеток в блоке
+fn mirror_block<const N: usize>(x: usize) -> usize {
+    (0..N).fold(0, |acc, i| acc | (((x >> i) & 1) << (N - 1 - i)))
+}
+
+impl<const N: usize> BlockRule<N> {
+    /// Правило, переводящее `p[i]` обратно в `i`. Шаг назад это его фазы в обратном порядке.
+    pub fn invert_time(&self) -> Self {
+        self.inverse()
+    }
+
+    /// Правило для поля с инвертированными клетками.
+    pub fn invert_color(&self) -> Self {
+        let mask = BlockRule::<N>::STATES - 1;
+        let p = self.permutation();
+        Self::from_permutation(
+            &(0..Self::STATES)
+                .map(|i| !p[!i & mask] & mask)
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// Инвертирует только результат правила.
+    pub fn invert_half_color(&self) -> Self {
+        let mask = BlockRule::<N>::STATES - 1;
+        let p = self.permutation();
+        Self::from_permutation(&p.iter().map(|to| !to & mask).collect::<Vec<_>>())
+    }
+
+    /// Правило для зеркально отражённого поля, фазы при этом идут в порядке `0, N - 1, ..., 1`.
+    pub fn mirror(&self) -> Self {
+        let p = self.permutation();
+        Self::from_permutation(
+            &(0..Self::STATES)
+                .map(|i| mirror_block::<N>(p[mirror_block::<N>(i)]))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// Зеркало и обращение времени вместе: отражённое поле, шагающее назад, эволюционирует по
+    /// этому правилу с обычным порядком фаз, но на поле, сдвинутом на одну клетку.
+    pub fn mirror_time(&self) -> Self {
+        self.invert_time().mirror()
+    }
+
+    /// Порядок фаз, с которым работает [`BlockRule::mirror`].
+    pub fn mirror_schedule(&self) -> Schedule<N> {
+        let offsets = (0..N as u32)
+            .map(|i| (N as u32 - i) % N as u32)
+            .collect::<Vec<_>>();
+        Schedule::with_offsets(&self.mirror(), &offsets)
+    }
+
+    /// Порядок фаз, с которым работает [`BlockRule::mirror_time`].
+    pub fn mirror_time_schedule(&self) -> Schedule<N> {
+        let offsets = (0..N as u32)
+            .map(|i| (i + 1) % N as u32)
+            .collect::<Vec<_>>();
+        Schedule::with_offsets(&self.mirror_time(), &offsets)
+    }
+
+    pub fn is_time_symmetrical(&self) -> bool {
+        *self == self.invert_time()
+    }
+
+    pub fn is_anti_time_symmetrical(&self) -> bool {
+        self.invert_color() == self.invert_time()
+    }
+
+    pub fn is_self_inverse(&self) -> bool {
+        *self == self.invert_color()
+    }
+
+    pub fn is_self_mirrored(&self) -> bool {
+        *self == self.mirror()
+    }
+
+    pub fn is_self_mirrored_time(&self) -> bool {
+        *self == self.mirror_time()
+    }
+
+    /// Каждый блок переходит в блок с тем же количеством единиц, значит сохраняется и
+    /// количество единиц во всём поле.
+    pub fn is_save_count(&self) -> bool {
+        self.permutation()
+            .iter()
+            .enumerate()
+            .all(|(i, to)| i.count_ones() == to.count_ones())
+    }
+}
+
+impl<const N: usize> BlockField<N> {
+    /// Инвертирует все клетки.
+    pub fn invert_color(self) -> Self {
+        Self::new(!self.val & low_mask(self.size), self.size)
+    }
+
+    /// Зеркальное отражение `i -> size - 1 - i`.
+    pub fn mirror(self) -> Self {
+        Self::new(self.val.reverse_bits() >> (64 - self.size), self.size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window3::{Field, Rule};
+    use crate::Random;
+
+    fn check_symmetries<const N: usize>(rule: &BlockRule<N>, random: &mut Random) {
+        let size = BlockField::<N>::MAX_SIZE;
+        for _ in 0..20 {
+            let x = BlockField::<N>::new(random.get() & low_mask(size), size);
+            let step = |rule: &dyn Fn(&mut BlockField<N>), mut x: BlockField<N>| {
+                rule(&mut x);
+                x
+            };
+
+            let forward = step(&|x| rule.steps(x), x);
+            let backward = step(&|x| rule.steps_back(x), x);
+
+            assert_eq!(
+                step(&|x| rule.invert_color().steps(x), x.invert_color()),
+                forward.invert_color()
+            );
+            assert_eq!(
+                step(&|x| rule.mirror_schedule().steps(x), x.mirror()),
+                forward.mirror()
+            );
+            assert_eq!(
+                step(&|x| rule.mirror_time_schedule().steps(x), x.mirror()),
+                backward.mirror()
+            );
+            // сдвиг на клетку переводит порядок фаз `1, ..., N - 1, 0` в обычный
+            assert_eq!(
+                step(&|x| rule.mirror_time().steps(x), x.mirror().rotate_right(1)),
+                backward.mirror().rotate_right(1)
+            );
+            let mut y = forward;
+            Schedule::with_offsets(
+                &rule.invert_time(),
+                &(0..N as u32).rev().collect::<Vec<_>>(),
+            )
+            .steps(&mut y);
+            assert_eq!(y, x);
+            if rule.is_save_count() {
+                assert_eq!(forward.val().count_ones(), x.val().count_ones());
+            }
+        }
+    }
+
+    #[test]
+    fn symmetries_match_simulation() {
+        let mut random = Random::new(25025069121927896);
+        for ni in [0, 173, 633, 876, 20000, 40319] {
+            check_symmetries(&Rule::num_to_rule(ni), &mut random);
+        }
+        check_symmetries(&BlockRule::<2>::num_to_rule(13), &mut random);
+        check_symmetries(&BlockRule::<4>::num_to_rule(123_456_789), &mut random);
+        check_symmetries(&BlockRule::<5>::num_to_rule(987_654_321_987), &mut random);
+    }
+
+    #[test]
+    fn involutions() {
+        for ni in [173, 633, 20000] {
+            let rule = Rule::num_to_rule(ni);
+            assert_eq!(rule.invert_time().invert_time(), rule);
+            assert_eq!(rule.invert_color().invert_color(), rule);
+            assert_eq!(rule.mirror().mirror(), rule);
+            assert_eq!(rule.mirror_time().mirror_time(), rule);
+        }
+        let x = Field::new(0b110_001, 6);
+        assert_eq!(x.mirror(), Field::new(0b100_011, 6));
+    }
+
+    #[test]
+    fn properties() {
+        let swap = |pairs: &[(usize, usize)]| {
+            let mut p = (0..8).collect::<Vec<_>>();
+            for (a, b) in pairs {
+                p.swap(*a, *b);
+            }
+            Rule::from_permutation(&p)
+        };
+        let mut cycle = (0..8).collect::<Vec<_>>();
+        cycle[1] = 2;
+        cycle[2] = 4;
+        cycle[4] = 1;
+        let cycle = Rule::from_permutation(&cycle);
+
+        let mut random = Random::new(25025069121927896);
+        let fields = (0..20)
+            .map(|_| Field::new(random.get() & low_mask(60), 60))
+            .collect::<Vec<_>>();
+        let holds = |property: &dyn Fn(Field) -> bool| fields.iter().all(|x| property(*x));
+        // шаг вперёд и затем фазы в обратном порядке с тем же правилом возвращают поле
+        let time_symmetrical = |rule: &Rule| {
+            holds(&|mut x| {
+                let start = x;
+                rule.steps(&mut x);
+                Schedule::with_offsets(rule, &[2, 1, 0]).steps(&mut x);
+                x == start
+            })
+        };
+        // инвертированное поле переходит в инвертированное
+        let self_inverse = |rule: &Rule| {
+            holds(&|x| {
+                let (mut y, mut z) = (x, x.invert_color());
+                rule.steps(&mut y);
+                rule.steps(&mut z);
+                z == y.invert_color()
+            })
+        };
+        // отражённое поле переходит в отражённое тем же правилом с фазами `0, 2, 1`
+        let self_mirrored = |rule: &Rule| {
+            holds(&|x| {
+                let (mut y, mut z) = (x, x.mirror());
+                rule.steps(&mut y);
+                Schedule::with_offsets(rule, &[0, 2, 1]).steps(&mut z);
+                z == y.mirror()
+            })
+        };
+        let save_count = |rule: &Rule| {
+            holds(&|x| {
+                let mut y = x;
+                rule.steps_count(&mut y, 5);
+                y.val().count_ones() == x.val().count_ones()
+            })
+        };
+
+        for (rule, expected) in [
+            (Rule::RULE0, [true, true, true, true]),
+            (swap(&[(1, 4), (3, 6)]), [true, true, true, true]),
+            (swap(&[(1, 2), (5, 6)]), [true, true, false, true]),
+            (cycle, [false, false, false, true]),
+            (swap(&[(1, 3)]), [true, false, false, false]),
+        ] {
+            let flags = [
+                rule.is_time_symmetrical(),
+                rule.is_self_inverse(),
+                rule.is_self_mirrored(),
+                rule.is_save_count(),
+            ];
+            assert_eq!(flags, expected, "{:?}", rule);
+            let simulated = [
+                time_symmetrical(&rule),
+                self_inverse(&rule),
+                self_mirrored(&rule),
+                save_count(&rule),
+            ];
+            assert_eq!(simulated, expected, "{:?}", rule);
+        }
+    }
+}
@@ -0,0 +1,163 @@
+//! Двумерное время для пары коммутирующих эволюций.
+//!
+//! Если `A B = B A`, то состояние `A^i B^j x` не зависит от порядка шагов, и у поля есть
+//! двумерное время `(i, j)`. Здесь строится вся решётка таких состояний, проверяется, что
+//! результат действительно не зависит от пути, и ищутся паттерны, повторяющиеся со сдвигом
+//! в плоскости времени.
+
+use super::*;
+
+/// Состояния `A^i B^j x` для `i < height`, `j < width`.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct TimeLattice<const N: usize> {
+    states: Vec<Vec<BlockField<N>>>,
+}
+
+impl<const N: usize> TimeLattice<N> {
+    /// Строит решётку: сначала `B^j x` по строке 0, затем каждый столбец шагами `A`.
+    pub fn new<A: Evolution<N>, B: Evolution<N>>(
+        a: &A,
+        b: &B,
+        x: BlockField<N>,
+        height: usize,
+        width: usize,
+    ) -> Self {
+        let mut row = vec![x];
+        for j in 1..width {
+            let mut y = row[j - 1];
+            b.steps(&mut y);
+            row.push(y);
+        }
+        let mut states = vec![row];
+        for i in 1..height {
+            let mut row = states[i - 1].clone();
+            for y in &mut row {
+                a.steps(y);
+            }
+            states.push(row);
+        }
+        Self { states }
+    }
+
+    pub fn height(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn width(&self) -> usize {
+        self.states[0].len()
+    }
+
+    /// Состояние `A^i B^j x`.
+    pub fn get(&self, i: usize, j: usize) -> BlockField<N> {
+        self.states[i][j]
+    }
+
+    /// Проверяет, что состояние в каждой точке не зависит от пути: решётка строилась шагами
+    /// `A` по столбцам, поэтому достаточно проверить шаги `B` по строкам. Возвращает первую
+    /// точку `(i, j)`, в которую шаг `B` из `(i, j - 1)` приводит в другое состояние.
+    pub fn check_path_independence<B: Evolution<N>>(&self, b: &B) -> Result<(), (usize, usize)> {
+        for i in 1..self.height() {
+            for j in 1..self.width() {
+                let mut y = self.states[i][j - 1];
+                b.steps(&mut y);
+                if y != self.states[i][j] {
+                    return Err((i, j));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Эволюция по времени `A` при фиксированном времени `B` равном `j`.
+    pub fn column(&self, j: usize) -> Vec<BlockField<N>> {
+        self.states.iter().map(|row| row[j]).collect()
+    }
+
+    /// Эволюция по времени `B` при фиксированном времени `A` равном `i`.
+    pub fn row(&self, i: usize) -> Vec<BlockField<N>> {
+        self.states[i].clone()
+    }
+
+    /// Рисует столбец `j`: строка картинки `i` это состояние `A^i B^j x`.
+    pub fn show_column(&self, j: usize, filename: &str) {
+        draw_image(
+            filename,
+            self.column(j).iter().map(|x| x.to_bools(1)).collect(),
+        );
+    }
+
+    /// Рисует строку `i`: строка картинки `j` это состояние `A^i B^j x`.
+    pub fn show_row(&self, i: usize, filename: &str) {
+        draw_image(
+            filename,
+            self.row(i).iter().map(|x| x.to_bools(1)).collect(),
+        );
+    }
+
+    /// Рисует лист времени для клетки `pos`: пиксель `(j, i)` это клетка `pos` состояния
+    /// `A^i B^j x`.
+    pub fn show_cell(&self, pos: u32, filename: &str) {
+        draw_image(
+            filename,
+            self.states
+                .iter()
+                .map(|row| row.iter().map(|x| x.index(pos)).collect())
+                .collect(),
+        );
+    }
+
+    /// Все сдвиги `(di, dj, offset)` в пределах решётки, кроме нулевого, после которых
+    /// состояние `x` повторяется со смещением `offset` блоков, `|offset| <= max_offset`.
+    /// Для глайдера это его скорости в плоскости времени.
+    pub fn find_time_periods(&self, max_offset: u32) -> Vec<(usize, usize, i32)> {
+        let x = self.states[0][0];
+        let mut result = Vec::new();
+        for (di, row) in self.states.iter().enumerate() {
+            for (dj, y) in row.iter().enumerate() {
+                if di == 0 && dj == 0 {
+                    continue;
+                }
+                let offset = (-(max_offset as i32)..=max_offset as i32)
+                    .find(|offset| y.rotate(offset * N as i32) == x);
+                if let Some(offset) = offset {
+                    result.push((di, dj, offset));
+                }
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window3::{Field, Rule};
+    use crate::Random;
+
+    #[test]
+    fn powers_of_one_rule() {
+        let a = Rule::num_to_rule(633);
+        let b = schedule::Schedule::with_offsets(&a, &[0, 1, 2, 0, 1, 2]);
+        let lattice = TimeLattice::new(&a, &b, Field::new(0b101, 30), 10, 10);
+        assert_eq!(lattice.check_path_independence(&b), Ok(()));
+
+        let (period, offset) = gliders::exact_period(Field::new(0b101, 30), &a, None).unwrap();
+        let periods = lattice.find_time_periods(4);
+        assert!(periods.contains(&(period as usize, 0, offset)));
+        if period % 2 == 0 {
+            assert!(periods.contains(&(0, period as usize / 2, offset)));
+        }
+        let mut x = lattice.get(3, 4);
+        a.steps_back_count(&mut x, 11);
+        assert_eq!(x, lattice.get(0, 0));
+    }
+
+    #[test]
+    fn not_commuting() {
+        let mut random = Random::new(25025069121927896);
+        let a = Rule::num_to_rule(633);
+        let b = Rule::num_to_rule(20000);
+        let lattice = TimeLattice::new(&a, &b, Field::new(random.get() & low_mask(63), 63), 5, 5);
+        assert!(lattice.check_path_independence(&b).is_err());
+    }
+}
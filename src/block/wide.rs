@@ -0,0 +1,319 @@
+//! Циклическое поле произвольной длины из нескольких `u64`.
+//!
+//! Каждое слово хранит [`BlockField::MAX_SIZE`] клеток, последнее слово может быть заполнено
+//! частично. Границы слов совпадают с границами блоков, поэтому правило применяется к каждому
+//! слову отдельно, а при сдвиге фазы биты переносятся между соседними словами.
+
+use super::*;
+use crate::Random;
+use std::cmp::Ordering;
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct WideField<const N: usize> {
+    words: Vec<u64>,
+    size: u32,
+}
+
+impl<const N: usize> WideField<N> {
+    /// Количество клеток в одном слове.
+    pub const WORD_SIZE: u32 = BlockField::<N>::MAX_SIZE;
+
+    /// Пустое поле из `size` клеток.
+    pub fn new(size: u32) -> Self {
+        assert!((2..=5).contains(&N));
+        assert!(size != 0 && size.is_multiple_of(N as u32));
+        let len = size.div_ceil(Self::WORD_SIZE) as usize;
+        Self {
+            words: vec![0; len],
+            size,
+        }
+    }
+
+    /// Случайное поле из `size` клеток, каждая клетка единица с вероятностью `density`.
+    pub fn random(random: &mut Random, size: u32, density: f64) -> Self {
+        let mut x = Self::new(size);
+        for i in 0..size {
+            x.set(i, random.next_double() < density);
+        }
+        x
+    }
+
+    /// Поле из `size` клеток, в младших клетках которого записано `val`.
+    pub fn from_u64(val: u64, size: u32) -> Self {
+        assert!(occupied_size(val) <= size);
+        let mut result = Self::new(size);
+        for i in 0..occupied_size(val) {
+            result.set(i, (val >> i) & 1 != 0);
+        }
+        result
+    }
+
+    /// Поле из `size` клеток, в младших клетках которого записано `x`.
+    pub fn from_field(x: BlockField<N>, size: u32) -> Self {
+        assert!(x.size() <= size);
+        Self::from_u64(x.val(), size)
+    }
+
+    /// Поле из массива клеток в порядке [`WideField::to_bools`], старшая клетка первая.
+    pub fn from_bools(cells: &[bool]) -> Self {
+        let size = cells.len() as u32;
+        let mut result = Self::new(size);
+        for (i, cell) in cells.iter().rev().enumerate() {
+            result.set(i as u32, *cell);
+        }
+        result
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    fn width(&self, word: usize) -> u32 {
+        if word + 1 < self.words.len() {
+            Self::WORD_SIZE
+        } else {
+            self.size - Self::WORD_SIZE * (self.words.len() as u32 - 1)
+        }
+    }
+
+    fn min_width(&self) -> u32 {
+        self.width(self.words.len() - 1)
+    }
+
+    pub fn index(&self, pos: u32) -> bool {
+        let pos = pos % self.size;
+        let (word, bit) = (pos / Self::WORD_SIZE, pos % Self::WORD_SIZE);
+        (self.words[word as usize] >> bit) & 1 != 0
+    }
+
+    pub fn set(&mut self, pos: u32, value: bool) {
+        let pos = pos % self.size;
+        let (word, bit) = (pos / Self::WORD_SIZE, pos % Self::WORD_SIZE);
+        let word = &mut self.words[word as usize];
+        *word = (*word & !(1 << bit)) | ((value as u64) << bit);
+    }
+
+    pub fn count_ones(&self) -> u32 {
+        self.words.iter().map(|x| x.count_ones()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|x| *x == 0)
+    }
+
+    // сдвиг меньше ширины любого слова, биты переносятся через соседнее слово
+    fn shift_left_small(&mut self, count: u32) {
+        let last = self.words.len() - 1;
+        let mut carry = self.words[last] >> (self.width(last) - count);
+        for i in 0..self.words.len() {
+            let width = self.width(i);
+            let old = self.words[i];
+            self.words[i] = ((old << count) | carry) & low_mask(width);
+            carry = old >> (width - count);
+        }
+    }
+
+    fn shift_right_small(&mut self, count: u32) {
+        let mut carry = self.words[0] & low_mask(count);
+        for i in (0..self.words.len()).rev() {
+            let width = self.width(i);
+            let old = self.words[i];
+            self.words[i] = (old >> count) | (carry << (width - count));
+            carry = old & low_mask(count);
+        }
+    }
+
+    fn rotate_slow(&self, count: u32) -> Self {
+        let mut result = Self::new(self.size);
+        for i in 0..self.size {
+            if self.index(i) {
+                result.set(i + count, true);
+            }
+        }
+        result
+    }
+
+    pub fn rotate_left(mut self, mut count: u32) -> Self {
+        count %= self.size;
+        if count == 0 {
+            self
+        } else if count < self.min_width() {
+            self.shift_left_small(count);
+            self
+        } else {
+            self.rotate_slow(count)
+        }
+    }
+
+    pub fn rotate_right(mut self, mut count: u32) -> Self {
+        count %= self.size;
+        if count == 0 {
+            self
+        } else if count < self.min_width() {
+            self.shift_right_small(count);
+            self
+        } else {
+            self.rotate_slow(self.size - count)
+        }
+    }
+
+    pub fn rotate(self, count: i32) -> Self {
+        if count < 0 {
+            self.rotate_left((-count) as u32)
+        } else {
+            self.rotate_right(count as u32)
+        }
+    }
+
+    pub fn to_bools(&self, dupl: u64) -> Vec<bool> {
+        let size = self.size as usize;
+        let mut result = vec![false; size];
+        for i in 0..size {
+            result[size - i - 1] = self.index(i as u32);
+        }
+        let mut result2 = Vec::new();
+        for _ in 0..dupl {
+            result2.extend(result.iter());
+        }
+        result2
+    }
+
+    /// Сравнивает поля как числа, старшее слово последнее.
+    pub fn cmp_val(&self, other: &Self) -> Ordering {
+        self.words.iter().rev().cmp(other.words.iter().rev())
+    }
+
+    /// Количество занятых клеток, округлённое вверх до целого блока.
+    pub fn occupied_size(&self) -> u32 {
+        match self.words.iter().rposition(|x| *x != 0) {
+            Some(word) => {
+                word as u32 * Self::WORD_SIZE + occupied_size_n(self.words[word], N as u32)
+            }
+            None => 0,
+        }
+    }
+
+    /// Минимальное число среди всех поворотов поля на целое число блоков.
+    pub fn minimize(self) -> Self {
+        let mut my_min = self.clone();
+        let mut current = self;
+        for _ in 0..current.size / N as u32 {
+            current = current.rotate_left(N as u32);
+            if current.cmp_val(&my_min) == Ordering::Less {
+                my_min = current.clone();
+            }
+        }
+        my_min
+    }
+
+    pub fn centralize(self) -> Self {
+        let mut offset = (self.size - self.occupied_size()) / 2;
+        offset -= offset % N as u32;
+        self.rotate_left(offset)
+    }
+}
+
+impl<const N: usize> Cells<N> for WideField<N> {
+    fn apply_phase(&mut self, rule: &BlockRule<N>, phase: u32) {
+        let phase = phase % N as u32;
+        if phase != 0 {
+            self.shift_right_small(phase);
+        }
+        for i in 0..self.words.len() {
+            self.words[i] = rule.replace_word(self.words[i]) & low_mask(self.width(i));
+        }
+        if phase != 0 {
+            self.shift_left_small(phase);
+        }
+    }
+}
+
+impl<const N: usize> BlockRule<N> {
+    /// Рисует `steps` фаз эволюции широкого поля в `filename`.
+    pub fn show_wide_field(&self, mut x: WideField<N>, steps: u64, filename: &str) {
+        let mut result = vec![x.to_bools(1)];
+        for _ in 0..steps / N as u64 {
+            for phase in 0..N as u32 {
+                self.step(&mut x, phase);
+                result.push(x.to_bools(1));
+            }
+        }
+        draw_image(filename, result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Random;
+
+    fn check_against_naive<const N: usize>(ni: u64, size: u32) {
+        let rule = BlockRule::<N>::num_to_rule(ni);
+        let mut random = Random::new(25025069121927896);
+        let mut x = WideField::<N>::random(&mut random, size, 0.5);
+        let mut cells = (0..size).map(|i| x.index(i)).collect::<Vec<_>>();
+        let permutation = rule.permutation();
+        for _ in 0..20 {
+            rule.steps(&mut x);
+            for phase in 0..N {
+                naive_phase::<N>(
+                    &mut cells,
+                    phase,
+                    |old, pos| old[pos.rem_euclid(old.len() as i64) as usize],
+                    |block, _| permutation[block],
+                );
+            }
+            assert_eq!((0..size).map(|i| x.index(i)).collect::<Vec<_>>(), cells);
+        }
+    }
+
+    #[test]
+    fn steps_across_words() {
+        check_against_naive::<2>(13, 200);
+        check_against_naive::<3>(633, 63);
+        check_against_naive::<3>(633, 66);
+        check_against_naive::<3>(173, 189);
+        check_against_naive::<3>(40000, 300);
+        check_against_naive::<4>(123_456_789, 132);
+        check_against_naive::<5>(0, 125);
+    }
+
+    #[test]
+    fn same_as_block_field() {
+        let mut random = Random::new(25025069121927896);
+        for ni in [0, 173, 633, 40319] {
+            let rule = BlockRule::<3>::num_to_rule(ni);
+            let mut x = BlockField::<3>::new(random.get() & low_mask(45), 45);
+            let mut y = WideField::from_field(x, 45);
+            for _ in 0..20 {
+                rule.steps(&mut x);
+                rule.steps(&mut y);
+                assert_eq!(y, WideField::from_field(x, 45));
+            }
+            assert_eq!(
+                y.clone().minimize(),
+                WideField::from_field(x.minimize(), 45)
+            );
+        }
+    }
+
+    #[test]
+    fn rotations() {
+        let mut random = Random::new(25025069121927896);
+        for size in [3, 63, 66, 189, 300] {
+            let start = WideField::<3>::random(&mut random, size, 0.5);
+            for i in 0..size + 5 {
+                let left = start.clone().rotate_left(i);
+                for j in 0..size {
+                    assert_eq!(left.index(j + i), start.index(j));
+                }
+                assert_eq!(left.rotate_right(i), start);
+            }
+            assert_eq!(WideField::<3>::from_bools(&start.to_bools(1)), start);
+        }
+    }
+}
@@ -0,0 +1,422 @@
+//! Обратимые блочные автоматы с `K` цветами клеток.
+//!
+//! Клетка хранится в [`KaryRule::BITS`] битах, поле это один `u128`. Правило это перестановка
+//! `K^N` состояний блока, где состояние блока с клетками `c_0, ..., c_{N-1}` имеет номер
+//! `c_0 + c_1 * K + ... + c_{N-1} * K^{N-1}`. Шаг устроен так же, как в [`crate::block`]:
+//! `N` фаз, на фазе `i` правило применяется к блокам, сдвинутым на `i` клеток. При `K = 2`
+//! нумерация состояний совпадает с [`crate::block::BlockRule`].
+
+use crate::block::gliders::{self, Dynamics, Ring};
+use crate::{draw_color_image, PALETTE};
+use std::collections::BTreeSet;
+
+/// Перестановка `K^N` состояний блока и таблицы переходов для упакованных блоков вперёд и
+/// назад.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct KaryRule<const K: usize, const N: usize> {
+    permutation: Vec<usize>,
+    // упакованный блок -> упакованный блок, некорректные коды переходят сами в себя
+    table: Vec<u128>,
+    inverse_table: Vec<u128>,
+}
+
+/// Циклическое поле из `size` клеток, `size` кратен `N` и не больше [`KaryField::MAX_SIZE`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct KaryField<const K: usize, const N: usize> {
+    val: u128,
+    size: u32,
+}
+
+const fn bits_per_cell(k: usize) -> u32 {
+    usize::BITS - (k - 1).leading_zeros()
+}
+
+fn mask(bits: u32) -> u128 {
+    if bits >= 128 {
+        !0
+    } else {
+        (1 << bits) - 1
+    }
+}
+
+impl<const K: usize, const N: usize> KaryRule<K, N> {
+    /// Количество бит на клетку.
+    pub const BITS: u32 = bits_per_cell(K);
+    pub const STATES: usize = K.pow(N as u32);
+    const BLOCK_BITS: u32 = Self::BITS * N as u32;
+
+    /// Создаёт правило из перестановки: блок `i` переходит в блок `p[i]`. Цветов не больше,
+    /// чем в [`PALETTE`], иначе [`KaryRule::show_field`] нечем рисовать клетки.
+    pub fn from_permutation(p: &[usize]) -> Self {
+        assert!(K >= 2 && N >= 2);
+        assert!(K <= PALETTE.len());
+        assert!(Self::BLOCK_BITS <= 16);
+        assert_eq!(p.len(), Self::STATES);
+        let mut used = vec![false; Self::STATES];
+        for &i in p {
+            assert!(!used[i]);
+            used[i] = true;
+        }
+
+        let mut table = (0..1u128 << Self::BLOCK_BITS).collect::<Vec<_>>();
+        let mut inverse_table = table.clone();
+        for (i, &to) in p.iter().enumerate() {
+            table[Self::pack(i) as usize] = Self::pack(to);
+            inverse_table[Self::pack(to) as usize] = Self::pack(i);
+        }
+        Self {
+            permutation: p.to_vec(),
+            table,
+            inverse_table,
+        }
+    }
+
+    pub fn permutation(&self) -> &[usize] {
+        &self.permutation
+    }
+
+    // номер состояния блока -> упакованные клетки
+    fn pack(mut state: usize) -> u128 {
+        let mut result = 0;
+        for i in 0..N as u32 {
+            result |= ((state % K) as u128) << (i * Self::BITS);
+            state /= K;
+        }
+        result
+    }
+
+    /// Количество правил, `(K^N)!`, если оно помещается в `u128`.
+    pub fn count() -> Option<u128> {
+        (1..=Self::STATES as u128).try_fold(1u128, |acc, i| acc.checked_mul(i))
+    }
+
+    /// Правило с номером `ni`, перестановки нумеруются в лексикографическом порядке.
+    pub fn num_to_rule(mut ni: u128) -> Self {
+        let len = Self::STATES;
+        let mut digits = vec![0; len];
+        for i in 1..=len {
+            digits[len - i] = (ni % i as u128) as usize;
+            ni /= i as u128;
+        }
+        assert_eq!(ni, 0, "rule number is too big");
+        let mut rest = (0..len).collect::<Vec<_>>();
+        let p = digits
+            .into_iter()
+            .map(|i| rest.remove(i))
+            .collect::<Vec<_>>();
+        Self::from_permutation(&p)
+    }
+
+    /// Номер правила, обратная функция к [`KaryRule::num_to_rule`].
+    pub fn rule_to_num(&self) -> u128 {
+        let len = Self::STATES;
+        let mut rest = (0..len).collect::<Vec<_>>();
+        let mut result = 0u128;
+        for (i, to) in self.permutation.iter().enumerate() {
+            let digit = rest.iter().position(|x| x == to).unwrap();
+            rest.remove(digit);
+            result = result * (len - i) as u128 + digit as u128;
+        }
+        result
+    }
+
+    pub fn inverse(&self) -> Self {
+        let mut p = vec![0; Self::STATES];
+        for (i, &to) in self.permutation.iter().enumerate() {
+            p[to] = i;
+        }
+        Self::from_permutation(&p)
+    }
+
+    /// Применяет правило ко всем блокам поля, блоки выровнены по клетке 0.
+    pub fn replace_all(&self, x: &mut KaryField<K, N>) {
+        Self::replace_with(&self.table, x);
+    }
+
+    fn replace_with(table: &[u128], x: &mut KaryField<K, N>) {
+        let mut result = 0;
+        for i in 0..x.size / N as u32 {
+            let shift = i * Self::BLOCK_BITS;
+            let block = (x.val >> shift) & mask(Self::BLOCK_BITS);
+            result |= table[block as usize] << shift;
+        }
+        x.val = result;
+    }
+
+    /// Одна фаза: правило применяется к блокам, сдвинутым на `phase` клеток.
+    pub fn step(&self, x: &mut KaryField<K, N>, phase: u32) {
+        *x = x.rotate_right(phase);
+        self.replace_all(x);
+        *x = x.rotate_left(phase);
+    }
+
+    /// Полный шаг: все `N` фаз по порядку.
+    pub fn steps(&self, x: &mut KaryField<K, N>) {
+        for phase in 0..N as u32 {
+            self.step(x, phase);
+        }
+    }
+
+    pub fn steps_count(&self, x: &mut KaryField<K, N>, count: u64) {
+        for _ in 0..count {
+            self.steps(x);
+        }
+    }
+
+    /// Отменяет полный шаг: фазы `N - 1, ..., 0` с обратным правилом.
+    pub fn steps_back(&self, x: &mut KaryField<K, N>) {
+        for phase in (0..N as u32).rev() {
+            *x = x.rotate_right(phase);
+            Self::replace_with(&self.inverse_table, x);
+            *x = x.rotate_left(phase);
+        }
+    }
+
+    /// Рисует `steps` фаз эволюции в цвете в `filename`.
+    pub fn show_field(&self, mut x: KaryField<K, N>, steps: u64, dupl: u64, filename: &str) {
+        let mut result = vec![x.to_colors(dupl)];
+        for _ in 0..steps / N as u64 {
+            for phase in 0..N as u32 {
+                self.step(&mut x, phase);
+                result.push(x.to_colors(dupl));
+            }
+        }
+        draw_color_image(filename, result);
+    }
+}
+
+impl<const K: usize, const N: usize> KaryField<K, N> {
+    pub const MAX_SIZE: u32 = 128 / bits_per_cell(K) / N as u32 * N as u32;
+    const BITS: u32 = bits_per_cell(K);
+
+    pub fn new(val: u128, size: u32) -> Self {
+        assert!(size != 0 && size.is_multiple_of(N as u32) && size <= Self::MAX_SIZE);
+        assert_eq!(val & !mask(size * Self::BITS), 0);
+        let result = Self { val, size };
+        assert!((0..size).all(|i| (result.cell(i) as usize) < K));
+        result
+    }
+
+    /// Поле, в клетках которого записаны цифры числа `num` в системе счисления по основанию `K`,
+    /// младшая цифра в клетке 0.
+    pub fn from_number(mut num: u128, size: u32) -> Self {
+        let mut result = Self::new(0, size);
+        let mut i = 0;
+        while num != 0 {
+            result.set(i, (num % K as u128) as u8);
+            num /= K as u128;
+            i += 1;
+        }
+        result
+    }
+
+    pub fn from_cells(cells: &[u8], size: u32) -> Self {
+        let mut result = Self::new(0, size);
+        for (i, cell) in cells.iter().enumerate() {
+            result.set(i as u32, *cell);
+        }
+        result
+    }
+
+    pub fn val(&self) -> u128 {
+        self.val
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn cell(&self, pos: u32) -> u8 {
+        ((self.val >> ((pos % self.size) * Self::BITS)) & mask(Self::BITS)) as u8
+    }
+
+    pub fn set(&mut self, pos: u32, color: u8) {
+        assert!((color as usize) < K);
+        let shift = (pos % self.size) * Self::BITS;
+        self.val = (self.val & !(mask(Self::BITS) << shift)) | ((color as u128) << shift);
+    }
+
+    pub fn rotate_left(mut self, mut count: u32) -> Self {
+        count %= self.size;
+        if count != 0 {
+            let bits = self.size * Self::BITS;
+            let shift = count * Self::BITS;
+            self.val = ((self.val << shift) | (self.val >> (bits - shift))) & mask(bits);
+        }
+        self
+    }
+
+    pub fn rotate_right(self, count: u32) -> Self {
+        let count = count % self.size;
+        self.rotate_left(self.size - count)
+    }
+
+    pub fn rotate(self, count: i32) -> Self {
+        if count < 0 {
+            self.rotate_left((-count) as u32)
+        } else {
+            self.rotate_right(count as u32)
+        }
+    }
+
+    /// Цвета клеток в порядке [`crate::block::BlockField::to_bools`], старшая клетка первая.
+    pub fn to_colors(&self, dupl: u64) -> Vec<u8> {
+        let row = (0..self.size)
+            .rev()
+            .map(|i| self.cell(i))
+            .collect::<Vec<_>>();
+        (0..dupl).flat_map(|_| row.iter().cloned()).collect()
+    }
+
+    /// Количество занятых клеток, округлённое вверх до целого блока.
+    pub fn occupied_size(&self) -> u32 {
+        let cells = (128 - self.val.leading_zeros()).div_ceil(Self::BITS);
+        cells.div_ceil(N as u32) * N as u32
+    }
+
+    /// Минимальное число среди всех поворотов поля на целое число блоков.
+    pub fn minimize(mut self) -> Self {
+        let mut my_min = self.val;
+        for _ in 0..self.size / N as u32 {
+            self = self.rotate_left(N as u32);
+            my_min = my_min.min(self.val);
+        }
+        self.val = my_min;
+        self
+    }
+}
+
+impl<const K: usize, const N: usize> Ring for KaryField<K, N> {
+    type Key = u128;
+    const CELLS: u32 = N as u32;
+    const MAX_POSITIONS: u32 = KaryField::<K, N>::MAX_SIZE / N as u32;
+
+    fn key(&self) -> u128 {
+        self.val
+    }
+
+    fn positions(&self) -> u32 {
+        self.size / N as u32
+    }
+
+    fn occupied_cells(&self) -> u32 {
+        (128 - self.val.leading_zeros()).div_ceil(Self::BITS)
+    }
+
+    fn prefix(&self, cells: u32) -> Self {
+        Self::new(self.val & mask(cells * Self::BITS), self.size)
+    }
+
+    fn resize(&self, positions: u32) -> Self {
+        Self::new(self.val, positions * N as u32)
+    }
+
+    fn shift(self, count: i32) -> Self {
+        self.rotate(count * N as i32)
+    }
+
+    fn canonical(self) -> Self {
+        self.minimize()
+    }
+}
+
+impl<const K: usize, const N: usize> Dynamics<KaryField<K, N>> for KaryRule<K, N> {
+    fn advance(&self, x: &mut KaryField<K, N>) {
+        self.steps(x);
+    }
+}
+
+/// Находит глайдеры и осцилляторы, перебирая поля, записанные числами до `max_count` в системе
+/// счисления по основанию `K`, как [`gliders::get_gliders`].
+pub fn get_gliders<const K: usize, const N: usize>(
+    rule: &KaryRule<K, N>,
+    max_count: u64,
+    max_period: Option<u64>,
+    use_progress: bool,
+) -> BTreeSet<(u128, u64, i32)> {
+    gliders::find_gliders(
+        rule,
+        max_count,
+        |num| KaryField::from_number(num as u128, KaryField::<K, N>::MAX_SIZE),
+        max_period,
+        use_progress,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::BlockField;
+    use crate::window3::Rule;
+    use crate::Random;
+
+    #[test]
+    fn numbering() {
+        type Rule33 = KaryRule<3, 3>;
+        assert_eq!(Rule33::BITS, 2);
+        assert_eq!(Rule33::STATES, 27);
+        assert_eq!(
+            Rule33::num_to_rule(0).permutation(),
+            (0..27).collect::<Vec<_>>()
+        );
+        let mut random = Random::new(25025069121927896);
+        let count = Rule33::count().unwrap();
+        for _ in 0..100 {
+            let ni = ((random.get() as u128) << 64 | random.get() as u128) % count;
+            let rule = Rule33::num_to_rule(ni);
+            assert_eq!(rule.rule_to_num(), ni);
+            assert_eq!(rule.inverse().inverse(), rule);
+        }
+        assert_eq!(KaryRule::<4, 4>::count(), None);
+    }
+
+    #[test]
+    fn binary_same_as_block() {
+        let mut random = Random::new(25025069121927896);
+        for ni in [0, 173, 633, 20000] {
+            let rule = Rule::num_to_rule(ni);
+            let kary = KaryRule::<2, 3>::from_permutation(&rule.permutation());
+            let val = random.get() & !(1 << 63);
+            let mut x = BlockField::<3>::new(val, 63);
+            let mut y = KaryField::<2, 3>::new(val as u128, 63);
+            for _ in 0..20 {
+                rule.steps(&mut x);
+                kary.steps(&mut y);
+                assert_eq!(x.val() as u128, y.val());
+            }
+        }
+    }
+
+    #[test]
+    fn steps_back() {
+        let mut random = Random::new(25025069121927896);
+        let rule = KaryRule::<3, 3>::num_to_rule(123_456_789_012_345_678_901_234);
+        for _ in 0..100 {
+            let start = KaryField::<3, 3>::from_number(random.get() as u128, 63);
+            let mut x = start;
+            rule.steps(&mut x);
+            rule.steps_back(&mut x);
+            assert_eq!(x, start);
+        }
+    }
+
+    #[test]
+    fn gliders() {
+        // клетка цвета 1 движется вправо на блок за шаг, клетка цвета 2 стоит на месте
+        let mut p = (0..27).collect::<Vec<usize>>();
+        for (from, to) in [(1, 3), (3, 9), (9, 1)] {
+            p[from] = to;
+        }
+        let rule = KaryRule::<3, 3>::from_permutation(&p);
+        let moving = KaryField::<3, 3>::from_cells(&[1], 30);
+        let still = KaryField::<3, 3>::from_cells(&[2], 30);
+        assert_eq!(gliders::exact_period(moving, &rule, None), Some((1, 1)));
+        assert_eq!(gliders::exact_period(still, &rule, None), Some((1, 0)));
+        let found = get_gliders(&rule, 100, None, false);
+        assert!(found.contains(&(moving.val(), 1, 1)));
+        assert!(found.contains(&(still.val(), 1, 0)));
+        // две движущиеся клетки это составной паттерн
+        let pair = KaryField::<3, 3>::from_cells(&[1, 0, 0, 1], 30).minimize();
+        assert!(!found.iter().any(|(val, _, _)| *val == pair.val()));
+    }
+}
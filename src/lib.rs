@@ -1,3 +1,9 @@
+pub mod block;
+pub mod kary;
+pub mod margolus;
+pub mod second_order;
+pub mod window3;
+
 pub fn draw_image(filename: &str, array: Vec<Vec<bool>>) {
     use std::fs::File;
     use std::io::BufWriter;
@@ -14,13 +20,46 @@ pub fn draw_image(filename: &str, array: Vec<Vec<bool>>) {
 
     let data = array
         .iter()
-        .map(|x| x.iter())
-        .flatten()
+        .flat_map(|x| x.iter())
         .map(|x| (!*x) as u8 * 255)
         .collect::<Vec<u8>>();
     writer.write_image_data(&data).unwrap();
 }
 
+/// Цвета клеток для [`draw_color_image`]: 0 белый, 1 чёрный, дальше цветные.
+pub const PALETTE: [[u8; 3]; 8] = [
+    [255, 255, 255],
+    [0, 0, 0],
+    [220, 50, 47],
+    [38, 139, 210],
+    [133, 153, 0],
+    [181, 137, 0],
+    [108, 113, 196],
+    [42, 161, 152],
+];
+
+pub fn draw_color_image(filename: &str, array: Vec<Vec<u8>>) {
+    use std::fs::File;
+    use std::io::BufWriter;
+    use std::path::Path;
+
+    let path = Path::new(filename);
+    let file = File::create(path).unwrap();
+    let w = BufWriter::new(file);
+
+    let mut encoder = png::Encoder::new(w, array[0].len() as u32, array.len() as u32);
+    encoder.set_color(png::ColorType::RGB);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().unwrap();
+
+    let data = array
+        .iter()
+        .flat_map(|x| x.iter())
+        .flat_map(|x| PALETTE[*x as usize])
+        .collect::<Vec<u8>>();
+    writer.write_image_data(&data).unwrap();
+}
+
 pub struct Random {
     state: u64,
 }
@@ -41,7 +80,7 @@ impl Random {
     }
 
     pub fn next_double(&mut self) -> f64 {
-        (self.get() as f64) / (std::u64::MAX as f64)
+        (self.get() as f64) / (u64::MAX as f64)
     }
 
     pub fn new(seed: u64) -> Self {
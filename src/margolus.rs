@@ -0,0 +1,304 @@
+//! Двумерные обратимые автоматы с окрестностью Марголуса.
+//!
+//! Поле это тор из `height` строк по `width` клеток, каждая строка хранится в одном `u64`.
+//! Правило это перестановка 16 состояний блока 2x2. Полный шаг состоит из двух фаз: на фазе 0
+//! блоки начинаются в чётных строках и столбцах, на фазе 1 сдвинуты на одну клетку по
+//! диагонали. Бит `0` состояния блока это клетка `(x, y)`, бит `1` клетка `(x + 1, y)`,
+//! бит `2` клетка `(x, y + 1)`, бит `3` клетка `(x + 1, y + 1)`.
+
+use crate::block::{low_mask, replace};
+use crate::{draw_image, repeat_bit};
+
+const PAIRS: [u64; 4] = [
+    repeat_bit(0b00, 2),
+    repeat_bit(0b01, 2),
+    repeat_bit(0b10, 2),
+    repeat_bit(0b11, 2),
+];
+
+/// Перестановка 16 состояний блока 2x2: блок `i` переходит в блок `self.0[i]`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct MargolusRule(pub [u8; 16]);
+
+/// Тор из `height` строк по `width` клеток, обе стороны чётные, `width <= 64`.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct MargolusField {
+    rows: Vec<u64>,
+    width: u32,
+}
+
+// поворот блока на 180 градусов
+const fn rotate_180(x: u8) -> u8 {
+    ((x & 1) << 3) | ((x & 2) << 1) | ((x & 4) >> 1) | ((x & 8) >> 3)
+}
+
+impl MargolusRule {
+    pub const RULE0: Self = {
+        let mut result = [0; 16];
+        let mut i = 0;
+        while i < 16 {
+            result[i] = i as u8;
+            i += 1;
+        }
+        Self(result)
+    };
+
+    /// Блоки с двумя живыми клетками не меняются, остальные инвертируются, а блоки с тремя
+    /// живыми клетками ещё и поворачиваются на 180 градусов.
+    pub const CRITTERS: Self = {
+        let mut result = [0; 16];
+        let mut i = 0;
+        while i < 16 {
+            result[i] = match (i as u8).count_ones() {
+                2 => i as u8,
+                3 => rotate_180(!(i as u8) & 0b1111),
+                _ => !(i as u8) & 0b1111,
+            };
+            i += 1;
+        }
+        Self(result)
+    };
+
+    /// Billiard Ball Machine: одиночный шар переходит в противоположный угол, два шара на
+    /// диагонали переходят на другую диагональ, остальные блоки не меняются.
+    pub const BILLIARD_BALL: Self = {
+        let mut result = Self::RULE0.0;
+        let mut i = 0;
+        while i < 4 {
+            result[1 << i] = rotate_180(1 << i);
+            i += 1;
+        }
+        result[0b1001] = 0b0110;
+        result[0b0110] = 0b1001;
+        Self(result)
+    };
+
+    /// Пустой и полный блоки инвертируются, остальные не меняются.
+    pub const TRON: Self = {
+        let mut result = Self::RULE0.0;
+        result[0b0000] = 0b1111;
+        result[0b1111] = 0b0000;
+        Self(result)
+    };
+
+    pub fn new(x: [u8; 16]) -> Self {
+        let mut used = [false; 16];
+        for i in x {
+            assert!(!used[i as usize]);
+            used[i as usize] = true;
+        }
+        Self(x)
+    }
+
+    pub fn inverse(&self) -> Self {
+        let mut result = [0; 16];
+        for (i, to) in self.0.iter().enumerate() {
+            result[*to as usize] = i as u8;
+        }
+        Self(result)
+    }
+
+    /// Применяет правило ко всем блокам пары строк `a` (верхняя) и `b`, блоки выровнены по
+    /// младшему биту.
+    pub fn replace_rows(&self, a: u64, b: u64) -> (u64, u64) {
+        let mut result_a = 0;
+        let mut result_b = 0;
+        for (i, to) in self.0.iter().map(|to| *to as usize).enumerate() {
+            let mask = replace::<2>(a, PAIRS[i & 3], !0) & replace::<2>(b, PAIRS[i >> 2], !0);
+            result_a |= mask & PAIRS[to & 3];
+            result_b |= mask & PAIRS[to >> 2];
+        }
+        (result_a, result_b)
+    }
+
+    /// Одна фаза: на фазе 1 блоки сдвинуты на клетку вправо и вниз.
+    pub fn step(&self, x: &mut MargolusField, phase: u32) {
+        let shift = (phase % 2) as usize;
+        let height = x.rows.len();
+        let mask = low_mask(x.width);
+        for y in (0..height).step_by(2) {
+            let top = (y + shift) % height;
+            let bottom = (y + 1 + shift) % height;
+            let a = x.rotate_row_right(x.rows[top], shift as u32);
+            let b = x.rotate_row_right(x.rows[bottom], shift as u32);
+            let (a, b) = self.replace_rows(a, b);
+            x.rows[top] = x.rotate_row_left(a & mask, shift as u32);
+            x.rows[bottom] = x.rotate_row_left(b & mask, shift as u32);
+        }
+    }
+
+    /// Полный шаг: фазы 0 и 1.
+    pub fn steps(&self, x: &mut MargolusField) {
+        self.step(x, 0);
+        self.step(x, 1);
+    }
+
+    pub fn steps_count(&self, x: &mut MargolusField, count: u64) {
+        for _ in 0..count {
+            self.steps(x);
+        }
+    }
+
+    /// Отменяет полный шаг: фазы 1 и 0 с обратным правилом.
+    pub fn steps_back(&self, x: &mut MargolusField) {
+        let inverse = self.inverse();
+        inverse.step(x, 1);
+        inverse.step(x, 0);
+    }
+
+    /// Сохраняет каждую фазу `steps` шагов эволюции в `{dir}/{номер фазы}.png`.
+    pub fn show_frames(&self, mut x: MargolusField, steps: u64, dir: &str) {
+        std::fs::create_dir_all(dir).unwrap();
+        draw_image(&format!("{}/0.png", dir), x.to_bools());
+        for i in 0..steps * 2 {
+            self.step(&mut x, (i % 2) as u32);
+            draw_image(&format!("{}/{}.png", dir, i + 1), x.to_bools());
+        }
+    }
+}
+
+impl MargolusField {
+    pub fn new(width: u32, height: u32) -> Self {
+        assert!(width.is_multiple_of(2) && (2..=64).contains(&width));
+        assert!(height.is_multiple_of(2) && height >= 2);
+        Self {
+            rows: vec![0; height as usize],
+            width,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.rows.len() as u32
+    }
+
+    pub fn rows(&self) -> &[u64] {
+        &self.rows
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> bool {
+        let (x, y) = (x % self.width, y % self.height());
+        (self.rows[y as usize] >> x) & 1 != 0
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, value: bool) {
+        let (x, y) = (x % self.width, y % self.height());
+        let row = &mut self.rows[y as usize];
+        *row = (*row & !(1 << x)) | ((value as u64) << x);
+    }
+
+    pub fn count_ones(&self) -> u32 {
+        self.rows.iter().map(|x| x.count_ones()).sum()
+    }
+
+    fn rotate_row_left(&self, row: u64, count: u32) -> u64 {
+        if count == 0 {
+            row
+        } else {
+            ((row << count) | (row >> (self.width - count))) & low_mask(self.width)
+        }
+    }
+
+    fn rotate_row_right(&self, row: u64, count: u32) -> u64 {
+        self.rotate_row_left(row, (self.width - count) % self.width)
+    }
+
+    /// Строки поля для [`draw_image`], столбец 0 слева.
+    pub fn to_bools(&self) -> Vec<Vec<bool>> {
+        (0..self.height())
+            .map(|y| (0..self.width).map(|x| self.get(x, y)).collect())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Random;
+
+    fn random_field(random: &mut Random, width: u32, height: u32) -> MargolusField {
+        let mut x = MargolusField::new(width, height);
+        for row in &mut x.rows {
+            *row = random.get() & low_mask(width);
+        }
+        x
+    }
+
+    // фаза по блокам 2×2 с явными координатами клеток, без сдвигов строк
+    fn naive_phase(x: &MargolusField, rule: &MargolusRule, phase: u32) -> MargolusField {
+        let mut result = x.clone();
+        let shift = phase % 2;
+        for by in (0..x.height()).step_by(2) {
+            for bx in (0..x.width()).step_by(2) {
+                let cells = [(0, 0), (1, 0), (0, 1), (1, 1)]
+                    .map(|(dx, dy)| (bx + dx + shift, by + dy + shift));
+                let block = cells
+                    .iter()
+                    .enumerate()
+                    .fold(0, |acc, (i, (cx, cy))| acc | ((x.get(*cx, *cy) as u8) << i));
+                let to = rule.0[block as usize];
+                for (i, (cx, cy)) in cells.iter().enumerate() {
+                    result.set(*cx, *cy, (to >> i) & 1 != 0);
+                }
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn same_as_naive() {
+        let mut random = Random::new(25025069121927896);
+        for rule in [
+            MargolusRule::CRITTERS,
+            MargolusRule::BILLIARD_BALL,
+            MargolusRule::TRON,
+        ] {
+            for (width, height) in [(8, 6), (30, 20), (64, 64)] {
+                let mut x = random_field(&mut random, width, height);
+                for i in 0..20 {
+                    let expected = naive_phase(&x, &rule, i % 2);
+                    rule.step(&mut x, i % 2);
+                    assert_eq!(x, expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn steps_back() {
+        let mut random = Random::new(25025069121927896);
+        for rule in [
+            MargolusRule::CRITTERS,
+            MargolusRule::BILLIARD_BALL,
+            MargolusRule::TRON,
+        ] {
+            let start = random_field(&mut random, 40, 30);
+            let mut x = start.clone();
+            rule.steps_count(&mut x, 10);
+            for _ in 0..10 {
+                rule.steps_back(&mut x);
+            }
+            assert_eq!(x, start);
+        }
+    }
+
+    #[test]
+    fn billiard_ball() {
+        let rule = MargolusRule::BILLIARD_BALL;
+        let mut x = MargolusField::new(16, 16);
+        x.set(4, 4, true);
+        rule.steps(&mut x);
+        let mut expected = MargolusField::new(16, 16);
+        expected.set(6, 6, true);
+        assert_eq!(x, expected);
+
+        let mut random = Random::new(25025069121927896);
+        let mut x = random_field(&mut random, 32, 32);
+        let count = x.count_ones();
+        rule.steps_count(&mut x, 50);
+        assert_eq!(x.count_ones(), count);
+    }
+}
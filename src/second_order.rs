@@ -0,0 +1,360 @@
+//! Обратимые автоматы второго порядка (конструкция Фредкина).
+//!
+//! Для любого локального правила `f` эволюция `x_{t+1} = f(x_t) XOR x_{t-1}` обратима:
+//! `x_{t-1} = f(x_t) XOR x_{t+1}`, то есть шаг назад это тот же шаг для пары, записанной в
+//! обратном порядке. Состояние поля это пара `(x_{t-1}, x_t)`, каждое из которых упаковано в
+//! `u64` так же, как [`crate::block::BlockField`]. Правило `f` задаётся таблицей на окне из
+//! `2 * radius + 1` клеток: бит `k` номера окна это клетка `i + k - radius`, поэтому при
+//! `radius = 1` номер правила совпадает с нумерацией Вольфрама для картинок
+//! [`SecondOrderField::to_bools`], где старшая клетка слева.
+
+use crate::block::gliders::{self, Dynamics, Ring};
+use crate::block::{low_mask, occupied_size};
+use crate::draw_image;
+use std::collections::BTreeSet;
+
+/// Локальное правило `f` с окном `2 * radius + 1`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct SecondOrderRule {
+    table: u64,
+    radius: u32,
+}
+
+/// Циклическое поле из `size` клеток: предыдущее и текущее состояние.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct SecondOrderField {
+    prev: u64,
+    cur: u64,
+    size: u32,
+}
+
+impl SecondOrderRule {
+    /// Правило с таблицей `table`: бит `j` это значение `f` на окне с номером `j`.
+    pub fn new(table: u64, radius: u32) -> Self {
+        assert!((1..=2).contains(&radius));
+        assert_eq!(table & !low_mask(1 << (2 * radius + 1)), 0);
+        Self { table, radius }
+    }
+
+    /// Элементарное правило с номером Вольфрама `ni`, из него получается правило `ni`R.
+    pub fn elementary(ni: u8) -> Self {
+        Self::new(ni as u64, 1)
+    }
+
+    pub fn table(&self) -> u64 {
+        self.table
+    }
+
+    pub fn radius(&self) -> u32 {
+        self.radius
+    }
+
+    /// Количество правил с окном `2 * radius + 1`.
+    pub fn count(radius: u32) -> u64 {
+        1 << (1 << (2 * radius + 1))
+    }
+
+    /// Применяет `f` ко всем клеткам циклического поля `x` из `size` клеток.
+    pub fn apply(&self, x: u64, size: u32) -> u64 {
+        let width = 2 * self.radius + 1;
+        // neighbours[k] это поле, в клетке i которого стоит клетка i + k - radius
+        let neighbours = (0..width)
+            .map(|k| rotate(x, k as i32 - self.radius as i32, size))
+            .collect::<Vec<_>>();
+        let mut result = 0;
+        for window in (0..1u64 << width).filter(|window| (self.table >> window) & 1 != 0) {
+            result |= neighbours
+                .iter()
+                .enumerate()
+                .fold(low_mask(size), |acc, (k, n)| {
+                    acc & if (window >> k) & 1 != 0 { *n } else { !*n }
+                });
+        }
+        result & low_mask(size)
+    }
+
+    /// Один шаг: `(x_{t-1}, x_t) -> (x_t, f(x_t) XOR x_{t-1})`.
+    pub fn steps(&self, x: &mut SecondOrderField) {
+        let next = self.apply(x.cur, x.size) ^ x.prev;
+        x.prev = x.cur;
+        x.cur = next;
+    }
+
+    pub fn steps_count(&self, x: &mut SecondOrderField, count: u64) {
+        for _ in 0..count {
+            self.steps(x);
+        }
+    }
+
+    /// Отменяет шаг: шаг вперёд для пары в обратном порядке.
+    pub fn steps_back(&self, x: &mut SecondOrderField) {
+        *x = x.swap();
+        self.steps(x);
+        *x = x.swap();
+    }
+
+    pub fn steps_back_count(&self, x: &mut SecondOrderField, count: u64) {
+        for _ in 0..count {
+            self.steps_back(x);
+        }
+    }
+
+    /// Рисует текущее состояние на протяжении `steps` шагов в `filename`.
+    pub fn show_field(&self, mut x: SecondOrderField, steps: u64, dupl: u64, filename: &str) {
+        let mut result = vec![x.to_bools(dupl)];
+        for _ in 0..steps {
+            self.steps(&mut x);
+            result.push(x.to_bools(dupl));
+        }
+        draw_image(filename, result);
+    }
+}
+
+// циклический сдвиг: в клетке i оказывается клетка i + count
+fn rotate(x: u64, count: i32, size: u32) -> u64 {
+    let count = count.rem_euclid(size as i32) as u32;
+    if count == 0 {
+        x
+    } else {
+        ((x >> count) | (x << (size - count))) & low_mask(size)
+    }
+}
+
+impl SecondOrderField {
+    pub fn new(prev: u64, cur: u64, size: u32) -> Self {
+        assert!((1..=64).contains(&size));
+        assert_eq!(prev & !low_mask(size), 0);
+        assert_eq!(cur & !low_mask(size), 0);
+        Self { prev, cur, size }
+    }
+
+    /// Пара, у которой предыдущее состояние совпадает с текущим.
+    pub fn still(val: u64, size: u32) -> Self {
+        Self::new(val, val, size)
+    }
+
+    /// Пара из числа: чётные биты `num` идут в предыдущее состояние, нечётные в текущее.
+    /// Маленькие числа перебирают все пары с маленьким занятым размером.
+    pub fn from_number(num: u64, size: u32) -> Self {
+        let (mut prev, mut cur) = (0, 0);
+        for i in 0..32 {
+            prev |= ((num >> (2 * i)) & 1) << i;
+            cur |= ((num >> (2 * i + 1)) & 1) << i;
+        }
+        Self::new(prev, cur, size)
+    }
+
+    pub fn prev(&self) -> u64 {
+        self.prev
+    }
+
+    pub fn cur(&self) -> u64 {
+        self.cur
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Меняет местами предыдущее и текущее состояние, то есть обращает время.
+    pub fn swap(self) -> Self {
+        Self::new(self.cur, self.prev, self.size)
+    }
+
+    /// Поле, в клетке `i` которого стоит клетка `i + count`, как у
+    /// [`crate::block::BlockField::rotate`], но смещение в клетках.
+    pub fn rotate(self, count: i32) -> Self {
+        Self::new(
+            rotate(self.prev, count, self.size),
+            rotate(self.cur, count, self.size),
+            self.size,
+        )
+    }
+
+    pub fn to_bools(&self, dupl: u64) -> Vec<bool> {
+        let row = (0..self.size)
+            .rev()
+            .map(|i| (self.cur >> i) & 1 != 0)
+            .collect::<Vec<_>>();
+        (0..dupl).flat_map(|_| row.iter().cloned()).collect()
+    }
+
+    /// Количество занятых клеток в обоих состояниях.
+    pub fn occupied_size(&self) -> u32 {
+        occupied_size(self.prev | self.cur)
+    }
+
+    /// Минимальная пара среди всех поворотов поля. Сначала сравнивается занятый размер,
+    /// чтобы паттерн не разрезался границей поля.
+    pub fn minimize(self) -> Self {
+        (0..self.size as i32)
+            .map(|i| self.rotate(i))
+            .min_by_key(|x| (x.occupied_size(), x.cur, x.prev))
+            .unwrap()
+    }
+
+    pub fn centralize(self) -> Self {
+        self.rotate(-(((self.size - self.occupied_size()) / 2) as i32))
+    }
+}
+
+impl Ring for SecondOrderField {
+    /// Сначала занятый размер, как в [`SecondOrderField::minimize`].
+    type Key = (u32, u64, u64);
+    const CELLS: u32 = 1;
+    const MAX_POSITIONS: u32 = 64;
+
+    fn key(&self) -> Self::Key {
+        (self.occupied_size(), self.cur, self.prev)
+    }
+
+    fn positions(&self) -> u32 {
+        self.size
+    }
+
+    fn occupied_cells(&self) -> u32 {
+        self.occupied_size()
+    }
+
+    fn prefix(&self, cells: u32) -> Self {
+        Self::new(
+            self.prev & low_mask(cells),
+            self.cur & low_mask(cells),
+            self.size,
+        )
+    }
+
+    fn resize(&self, positions: u32) -> Self {
+        Self::new(self.prev, self.cur, positions)
+    }
+
+    fn shift(self, count: i32) -> Self {
+        self.rotate(count)
+    }
+
+    fn canonical(self) -> Self {
+        self.minimize()
+    }
+}
+
+impl Dynamics<SecondOrderField> for SecondOrderRule {
+    fn advance(&self, x: &mut SecondOrderField) {
+        self.steps(x);
+    }
+
+    /// Кольца для проверки глайдера удлиняются на ширину окна.
+    fn margin(&self) -> u32 {
+        2 * self.radius + 1
+    }
+}
+
+/// Находит глайдеры и осцилляторы среди пар [`SecondOrderField::from_number`] для чисел до
+/// `max_count`, как [`gliders::get_gliders`]. Возвращает `(prev, cur, period, offset)`, пары
+/// минимальны по всем поворотам и фазам, смещение в клетках.
+pub fn get_gliders(
+    rule: &SecondOrderRule,
+    max_count: u64,
+    max_period: Option<u64>,
+    use_progress: bool,
+) -> BTreeSet<(u64, u64, u64, i32)> {
+    gliders::find_gliders(
+        rule,
+        max_count,
+        |num| SecondOrderField::from_number(num, 64),
+        max_period,
+        use_progress,
+    )
+    .into_iter()
+    .map(|((_, cur, prev), period, offset)| (prev, cur, period, offset))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Random;
+
+    #[test]
+    fn apply_same_as_naive() {
+        let mut random = Random::new(25025069121927896);
+        for radius in [1, 2] {
+            for _ in 0..20 {
+                let rule =
+                    SecondOrderRule::new(random.get() & low_mask(1 << (2 * radius + 1)), radius);
+                let size = random.next_in_range(2 * radius as u64 + 1, 65) as u32;
+                let x = random.get() & low_mask(size);
+                let naive = (0..size).fold(0, |acc, i| {
+                    let window = (0..2 * radius + 1).fold(0, |w, k| {
+                        let pos = (i + size + k - radius) % size;
+                        w | ((x >> pos) & 1) << k
+                    });
+                    acc | ((rule.table >> window) & 1) << i
+                });
+                assert_eq!(rule.apply(x, size), naive);
+            }
+        }
+        assert_eq!(SecondOrderRule::count(1), 256);
+        assert_eq!(SecondOrderRule::count(2), 1 << 32);
+    }
+
+    #[test]
+    fn steps_back() {
+        let mut random = Random::new(25025069121927896);
+        for ni in [30, 90, 110, 150] {
+            let rule = SecondOrderRule::elementary(ni);
+            let start = SecondOrderField::new(random.get(), random.get(), 64);
+            let mut x = start;
+            rule.steps_count(&mut x, 100);
+            rule.steps_back_count(&mut x, 100);
+            assert_eq!(x, start);
+        }
+    }
+
+    #[test]
+    fn periods() {
+        // x_{t+1} = x_t XOR x_{t-1}: a, b, a ^ b, a, b, ...
+        let identity = SecondOrderRule::elementary(204);
+        let x = SecondOrderField::new(0b1011, 0b110, 20);
+        assert_eq!(gliders::exact_period(x, &identity, None), Some((3, 0)));
+        // f = 0: x_{t+1} = x_{t-1}
+        let zero = SecondOrderRule::elementary(0);
+        assert_eq!(gliders::exact_period(x, &zero, None), Some((2, 0)));
+    }
+
+    #[test]
+    fn gliders() {
+        // в 90R пара (e_1, e_0) переходит в (e_0, e_{-1})
+        let rule = SecondOrderRule::elementary(90);
+        let x = SecondOrderField::new(0b10, 0b1, 30).rotate(-5);
+        assert_eq!(gliders::exact_period(x, &rule, None), Some((1, -1)));
+        assert!(gliders::is_same_after(x, &rule, 10, -10));
+        assert_eq!(
+            gliders::is_this_glider(x, &rule, None)
+                .map(|(min, p, offset)| (min.prev, min.cur, p, offset)),
+            Some((0b10, 0b1, 1, -1))
+        );
+
+        let found = get_gliders(&rule, 1 << 10, None, false);
+        assert!(found.contains(&(0b10, 0b1, 1, -1)));
+        for (prev, cur, p, offset) in &found {
+            let x = SecondOrderField::new(*prev, *cur, 40).centralize();
+            assert_eq!(gliders::exact_period(x, &rule, None), Some((*p, *offset)));
+        }
+    }
+
+    #[test]
+    fn radius_two_gliders() {
+        // f = x_{i-2} XOR x_{i+2}: пара (e_2, e_0) переходит в (e_0, e_{-2})
+        let table = (0..32).fold(0, |acc, w: u64| acc | ((w ^ (w >> 4)) & 1) << w);
+        let rule = SecondOrderRule::new(table, 2);
+        let found = get_gliders(&rule, 1 << 12, Some(100), false);
+        assert!(found.contains(&(0b100, 0b1, 1, -2)));
+        for (prev, cur, p, offset) in &found {
+            let x = SecondOrderField::new(*prev, *cur, 60).centralize();
+            assert_eq!(
+                gliders::exact_period(x, &rule, Some(100)),
+                Some((*p, *offset))
+            );
+        }
+    }
+}
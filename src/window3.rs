@@ -0,0 +1,89 @@
+//! Обратимые автоматы с размером окна 3, частный случай [`crate::block`].
+//!
+//! Полный шаг состоит из трёх фаз: правило применяется к блокам из трёх клеток со
+//! смещением 0, 1 и 2.
+
+#![allow(clippy::unusual_byte_groupings)]
+
+use crate::block::*;
+use crate::repeat_bit;
+
+pub use crate::block::gliders;
+pub use crate::block::{occupied_size, replace, Cells, Evolution};
+
+pub const PAT0: u64 = repeat_bit(0b000, 3);
+pub const PAT1: u64 = repeat_bit(0b001, 3);
+pub const PAT2: u64 = repeat_bit(0b010, 3);
+pub const PAT3: u64 = repeat_bit(0b011, 3);
+pub const PAT4: u64 = repeat_bit(0b100, 3);
+pub const PAT5: u64 = repeat_bit(0b101, 3);
+pub const PAT6: u64 = repeat_bit(0b110, 3);
+pub const PAT7: u64 = repeat_bit(0b111, 3);
+
+pub type Rule = BlockRule<3>;
+pub type Field = BlockField<3>;
+
+/// Количество занятых бит, округлённое вверх до целого блока.
+pub fn occupied_size3(x: u64) -> u32 {
+    occupied_size_n(x, 3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Random;
+
+    #[test]
+    fn test() {
+        color_backtrace::install();
+
+        let rule1 = Rule::new(&[PAT0, PAT1, PAT2, PAT3, PAT4, PAT5, PAT7, PAT6]);
+
+        let mut x = Field::new(0b001_110_111, 63);
+        rule1.replace_all(&mut x);
+        assert_eq!(x, Field::new(0b001_111_110, 63));
+
+        let rule2 = Rule::new(&[PAT0, PAT2, PAT1, PAT4, PAT3, PAT6, PAT5, PAT7]);
+        let mut x = Field::new(0b001_010_011_100_101_110_111, 63);
+        rule2.replace_all(&mut x);
+        assert_eq!(x, Field::new(0b010_001_100_011_110_101_111, 63));
+
+        let x = Field::new(repeat_bit(0b01, 2), 63);
+        assert_eq!(x, x.rotate_right(1).rotate_left(1));
+        assert_eq!(x, x.rotate_left(1).rotate_right(1));
+
+        let mut random = Random::new(25025069121927896);
+        let start = Field::new(random.get() & low_mask(45), 45);
+        for i in 0..100 {
+            assert_eq!(start.rotate_left(i), {
+                let mut x = start;
+                for _ in 0..i {
+                    x = x.rotate_left(1);
+                }
+                x
+            });
+            assert_eq!(start.rotate_right(i), {
+                let mut x = start;
+                for _ in 0..i {
+                    x = x.rotate_right(1);
+                }
+                x
+            });
+        }
+    }
+
+    #[test]
+    fn steps_matches_phases() {
+        let mut random = Random::new(25025069121927896);
+        for ni in [0, 173, 633, 40319] {
+            let rule = Rule::num_to_rule(ni);
+            let mut x = Field::new(random.get() & low_mask(63), 63);
+            let mut y = x;
+            rule.steps(&mut x);
+            for phase in 0..3 {
+                rule.step(&mut y, phase);
+            }
+            assert_eq!(x, y);
+        }
+    }
+}